@@ -0,0 +1,410 @@
+//! Sensitivity analysis of the best route, i.e. how much the times of the connections can change before another route is faster.
+
+use crate::route::Route;
+use crate::solver::{self, Connection, SettingsError, SolverSettings, Stats, WarmStart};
+use crate::MISSING;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sensitivity {
+    /// Connection of the best route, which can get `slack` frames slower and the route is still the best one.
+    /// `None` if every route uses it, e.g. because it is forced.
+    Used { slack: Option<u32> },
+    /// Connection outside of the best route, which the best route would use if it took less than `below` frames.
+    Unused { below: u32 },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionSensitivity {
+    pub connection: Connection,
+    /// time of the connection in the table, `None` if it is missing
+    pub time: Option<u32>,
+    pub sensitivity: Sensitivity,
+}
+
+/// Largest table which [`analyze_sensitivity`] is run on unless asked for explicitly, bigger ones take minutes
+/// because the analysis solves the table again for each connection.
+pub const SENSITIVITY_MAX_MAPS: usize = 20;
+
+/// Best route if an existing connection got faster, see [`rank_improvements`]
+#[derive(Debug, Clone)]
+pub struct Improvement {
+    pub connection: Connection,
+    /// time of the connection in the table
+    pub time: u32,
+    /// how much faster the best route gets
+    pub savings: u32,
+    /// best route with the faster connection
+    pub route: Route,
+}
+
+/// Solves `table` once, then once per connection to find the range in which its time doesn't change the best route.
+///
+/// The ranges aren't derived from the bounds of a single search, but brute forced: there is a complete search without
+/// each connection of the best route, and one with each other connection which could be part of a route, unless its
+/// lower bound already rules it out. Each search is pruned by the time of the best route, but the total grows quickly
+/// with the size of the table: a 20 map lobby takes seconds, while 23 maps take minutes, see [`SENSITIVITY_MAX_MAPS`].
+///
+/// `emit_sensitivity` gets called for each connection of the best route, and each connection outside of it which
+/// could become part of it by getting faster. Returns the best route the analysis is about,
+/// or `None` if there is no route, or the search is stopped.
+///
+/// With a `restart_penalty`, the restart times of the table aren't used, so restarts are left out.
+pub fn analyze_sensitivity<F>(
+    table: &[Vec<u32>],
+    settings: &SolverSettings,
+    emit_sensitivity: F,
+) -> Result<Option<Route>, SettingsError>
+where
+    F: FnMut(ConnectionSensitivity),
+{
+    settings.check_table(table)?;
+    Ok(sensitivity(table, settings, emit_sensitivity))
+}
+
+fn sensitivity<F>(
+    table: &[Vec<u32>],
+    settings: &SolverSettings,
+    mut emit_sensitivity: F,
+) -> Option<Route>
+where
+    F: FnMut(ConnectionSensitivity),
+{
+    let route = solve_best(table, settings)?;
+    let search = ConnectionSearch::new(table, settings);
+
+    let used: Vec<Connection> = route.connections().collect();
+    for &connection in &used {
+        if search.skip_restart(connection) {
+            continue;
+        }
+
+        let slack = if settings.forced_connections.contains(&connection) {
+            None
+        } else {
+            let mut forbidden_connections = settings.forbidden_connections.clone();
+            forbidden_connections.push(connection);
+            let settings = SolverSettings {
+                forbidden_connections,
+                ..search.settings.clone()
+            };
+
+            let mut best = None;
+            let stats = solve(table, &settings, solver::emit_only_best(&mut best));
+            if !stats.complete {
+                return None;
+            }
+            best.map(|(_, other_time)| other_time - route.time)
+        };
+
+        emit_sensitivity(ConnectionSensitivity {
+            connection,
+            time: search.table_time(connection),
+            sensitivity: Sensitivity::Used { slack },
+        });
+    }
+
+    for connection in search.candidates(false) {
+        if used.contains(&connection) {
+            continue;
+        }
+
+        // the time the rest of the fastest route using the connection takes
+        if let Some((_, rest)) = search.best_with(connection, 0, route.time)? {
+            emit_sensitivity(ConnectionSensitivity {
+                connection,
+                time: search.table_time(connection),
+                sensitivity: Sensitivity::Unused {
+                    below: route.time - rest,
+                },
+            });
+        }
+    }
+
+    Some(route)
+}
+
+/// Best route for each existing connection of `table` if it were `improvement` frames faster,
+/// sorted by how much faster the best route gets, so the most valuable drafts to improve come first.
+///
+/// Only routes faster than the current best one have to be searched for, and the connections of the best route
+/// make it exactly as much faster as they improve. Connections whose lower bound with the improvement can't beat
+/// the best route are skipped without a search, and the others need only a heavily pruned one.
+/// Returns `None` if there is no route, or the search is stopped.
+///
+/// With a `restart_penalty`, the restart times of the table aren't used, so restarts are left out.
+pub fn rank_improvements(
+    table: &[Vec<u32>],
+    settings: &SolverSettings,
+    improvement: u32,
+) -> Result<Option<Vec<Improvement>>, SettingsError> {
+    settings.check_table(table)?;
+    Ok(improvements(table, settings, improvement))
+}
+
+fn improvements(
+    table: &[Vec<u32>],
+    settings: &SolverSettings,
+    improvement: u32,
+) -> Option<Vec<Improvement>> {
+    let route = solve_best(table, settings)?;
+    let search = ConnectionSearch::new(table, settings);
+
+    let used: Vec<Connection> = route.connections().collect();
+    let mut improvements = Vec::new();
+    for connection in search.candidates(true) {
+        let connection_time = table[connection.from][connection.to];
+        let improved_time = connection_time.saturating_sub(improvement);
+        let mut improved_table = table.to_vec();
+        improved_table[connection.from][connection.to] = improved_time;
+
+        let (maps, time) = if used.contains(&connection) {
+            let savings = connection_time - improved_time;
+            (route.maps.clone(), route.time - savings)
+        } else {
+            search
+                .best_with(connection, improved_time, route.time)?
+                .unwrap_or_else(|| (route.maps.clone(), route.time))
+        };
+
+        improvements.push(Improvement {
+            connection,
+            time: connection_time,
+            savings: route.time - time,
+            route: Route::new(&improved_table, settings, &maps, time),
+        });
+    }
+
+    improvements.sort_by_key(|improvement| std::cmp::Reverse(improvement.savings));
+    Some(improvements)
+}
+
+/// Solves with settings derived from ones which passed [`SolverSettings::check`] already.
+fn solve<F>(table: &[Vec<u32>], settings: &SolverSettings, emit_solution: F) -> Stats
+where
+    F: FnMut(&[usize], u32) -> u32,
+{
+    solver::solve_table(table, settings, emit_solution).expect("the settings are checked before")
+}
+
+fn solve_best(table: &[Vec<u32>], settings: &SolverSettings) -> Option<Route> {
+    let mut best = None;
+    let stats = solve(table, settings, solver::emit_only_best(&mut best));
+    best.filter(|_| stats.complete)
+        .map(|(maps, time)| Route::new(table, settings, &maps, time))
+}
+
+/// Searches for the fastest routes using a specific connection.
+struct ConnectionSearch<'a> {
+    table: &'a [Vec<u32>],
+    settings: SolverSettings,
+}
+
+impl<'a> ConnectionSearch<'a> {
+    fn new(table: &'a [Vec<u32>], settings: &SolverSettings) -> ConnectionSearch<'a> {
+        // known routes and times are for the unchanged table
+        let settings = SolverSettings {
+            warm_start: WarmStart {
+                routes: Vec::new(),
+                time: None,
+                solutions: 1,
+                ..settings.warm_start
+            },
+            ..settings.clone()
+        };
+        ConnectionSearch { table, settings }
+    }
+
+    fn table_time(&self, connection: Connection) -> Option<u32> {
+        Some(self.table[connection.from][connection.to]).filter(|&time| time < MISSING)
+    }
+
+    fn skip_restart(&self, connection: Connection) -> bool {
+        connection.to == self.settings.start && self.settings.restart_penalty.is_some()
+    }
+
+    /// connections which can be part of a route, only the ones in the table if `existing`
+    fn candidates(&self, existing: bool) -> impl Iterator<Item = Connection> + '_ {
+        let n = self.table.len();
+        let finish = self.settings.finish.single(n);
+        (0..n)
+            .filter(move |&from| Some(from) != finish)
+            .flat_map(move |from| (0..n).map(move |to| Connection { from, to }))
+            .filter(move |&connection| {
+                connection.from != connection.to
+                    && !self.skip_restart(connection)
+                    && !self.settings.forbidden_connections.contains(&connection)
+                    && (!existing || self.table_time(connection).is_some())
+            })
+    }
+
+    /// Fastest route using `connection` with a time of `connection_time`, if it is faster than `time_to_beat`.
+    /// Returns `None` if the search is stopped.
+    fn best_with(
+        &self,
+        connection: Connection,
+        connection_time: u32,
+        time_to_beat: u32,
+    ) -> Option<Option<(Vec<usize>, u32)>> {
+        let Some(time_to_beat) = time_to_beat.checked_sub(1) else {
+            return Some(None);
+        };
+
+        let mut table = self.table.to_vec();
+        table[connection.from][connection.to] = connection_time;
+
+        // forcing the connection leaves only the routes using it to search, and any faster route has to use it anyway
+        let mut forced_connections = self.settings.forced_connections.clone();
+        forced_connections.push(connection);
+        let forced = SolverSettings {
+            forced_connections,
+            ..self.settings.clone()
+        };
+        let settings = if forced.check(table.len()).is_ok() {
+            forced
+        } else {
+            self.settings.clone()
+        };
+        let settings = SolverSettings {
+            warm_start: WarmStart {
+                time: Some(time_to_beat),
+                ..settings.warm_start.clone()
+            },
+            ..settings
+        };
+
+        // like for new connections, the bound often shows without a search that the connection doesn't help
+        let lower_bound =
+            solver::root_lower_bound(&table, &settings).expect("the settings are checked before");
+        if lower_bound > time_to_beat {
+            return Some(None);
+        }
+
+        // routes slower than the known time aren't emitted
+        let mut best = None;
+        let stats = solve(&table, &settings, solver::emit_only_best(&mut best));
+        stats.complete.then_some(best)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{random_table, settings_variants, Rng};
+
+    fn best_time(table: &[Vec<u32>], settings: &SolverSettings) -> Option<u32> {
+        let mut best = None;
+        solver::solve_table(table, settings, solver::emit_only_best(&mut best)).unwrap();
+        best.map(|(_, time)| time)
+    }
+
+    #[test]
+    fn finds_the_times_at_which_the_best_route_changes() {
+        let mut rng = Rng::new(15);
+        let settings = SolverSettings::default();
+        let (mut used, mut unused) = (0, 0);
+        for case in 0..20 {
+            let n = 4 + case % 3;
+            let table = random_table(&mut rng, n, 40);
+            let with_time = |connection: Connection, time: u32| {
+                let mut table = table.clone();
+                table[connection.from][connection.to] = time;
+                best_time(&table, &settings)
+            };
+
+            let mut sensitivities = Vec::new();
+            let route = analyze_sensitivity(&table, &settings, |sensitivity| {
+                sensitivities.push(sensitivity)
+            })
+            .unwrap();
+            let Some(route) = route else {
+                assert_eq!(best_time(&table, &settings), None);
+                continue;
+            };
+
+            for ConnectionSensitivity {
+                connection,
+                time,
+                sensitivity,
+            } in sensitivities
+            {
+                match sensitivity {
+                    Sensitivity::Used { slack } => {
+                        used += 1;
+                        let time = time.unwrap();
+                        let slack = slack.unwrap_or(MISSING - 1 - time);
+                        assert_eq!(
+                            with_time(connection, time + slack),
+                            Some(route.time + slack)
+                        );
+                        if time + slack + 1 < MISSING {
+                            assert_eq!(
+                                with_time(connection, time + slack + 1),
+                                Some(route.time + slack)
+                            );
+                        }
+                    }
+                    Sensitivity::Unused { below } => {
+                        unused += 1;
+                        assert!(time.is_none_or(|time| time >= below), "{table:?}");
+                        assert_eq!(with_time(connection, below), Some(route.time));
+                        if below > 0 {
+                            assert_eq!(with_time(connection, below - 1), Some(route.time - 1));
+                        }
+                    }
+                }
+            }
+        }
+        assert!(used > 0 && unused > 0);
+    }
+
+    #[test]
+    fn ranks_drafts_by_what_improving_them_saves() {
+        let mut rng = Rng::new(16);
+        for case in 0..40 {
+            let n = 4 + case % 3;
+            let table = random_table(&mut rng, n, 40);
+            let variants = settings_variants(n);
+            let settings = &variants[case % variants.len()];
+            let Some(best) = best_time(&table, settings) else {
+                continue;
+            };
+            // big improvements make routes possible which the bound doesn't rule out
+            let by = if case % 2 == 0 { 50 } else { 400 };
+
+            let improvements = rank_improvements(&table, settings, by).unwrap().unwrap();
+            assert!(improvements
+                .windows(2)
+                .all(|pair| pair[0].savings >= pair[1].savings));
+            // every connection of the table which a route can take, except restarts replaced by the penalty
+            let finish = settings.finish.single(n);
+            let connections = (0..n)
+                .filter(|&from| Some(from) != finish)
+                .flat_map(|from| (0..n).map(move |to| Connection { from, to }))
+                .filter(|&Connection { from, to }| {
+                    from != to
+                        && table[from][to] < MISSING
+                        && !settings
+                            .forbidden_connections
+                            .contains(&Connection { from, to })
+                        && !(to == settings.start && settings.restart_penalty.is_some())
+                })
+                .count();
+            assert_eq!(improvements.len(), connections);
+            for improvement in &improvements {
+                let Connection { from, to } = improvement.connection;
+                assert_eq!(improvement.time, table[from][to]);
+
+                let mut improved = table.clone();
+                improved[from][to] = improvement.time.saturating_sub(by);
+                let improved_best = best_time(&improved, settings).unwrap();
+                assert_eq!(
+                    improvement.savings,
+                    best - improved_best,
+                    "{:?} in {table:?} with {settings:?}",
+                    improvement.connection
+                );
+                assert_eq!(improvement.route.time, improved_best);
+            }
+        }
+    }
+}
@@ -3,15 +3,280 @@
 static ALLOC: dhat::Alloc = dhat::Alloc;
 
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use trout::solver::CancellationToken;
 
-fn solve_table(table: &str) -> Result<()> {
-    let table = trout::parse_table(&table).context("could not parse table")?;
+struct Args {
+    paths: Vec<PathBuf>,
+    threads: usize,
+    max_solutions: usize,
+    min_difference: usize,
+    restart_penalty: Option<u32>,
+    backend: trout::solver::Backend,
+    lower_bound: trout::solver::LowerBound,
+    time_limit: Option<Duration>,
+    progress: bool,
+    sensitivity: bool,
+    force_sensitivity: bool,
+    rank_drafts: Option<u32>,
+    suggest_pairs: bool,
+    evaluate: Option<String>,
+    convert: Option<PathBuf>,
+    header: bool,
+    labels: bool,
+    missing: Vec<String>,
+    warm_start: trout::solver::WarmStart,
+    precedences: Vec<trout::solver::Precedence>,
+    optional_maps: trout::solver::OptionalMaps,
+    start: usize,
+    finish: trout::solver::Finish,
+    forced_connections: Vec<trout::solver::Connection>,
+    forbidden_connections: Vec<trout::solver::Connection>,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut paths = Vec::new();
+    let mut threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut max_solutions = 12;
+    let mut min_difference = 0;
+    let mut restart_penalty = None;
+    let mut backend = trout::solver::Backend::default();
+    let mut lower_bound = trout::solver::LowerBound::default();
+    let mut time_limit = None;
+    let mut progress = false;
+    let mut sensitivity = false;
+    let mut force_sensitivity = false;
+    let mut rank_drafts = None;
+    let mut suggest_pairs = false;
+    let mut evaluate = None;
+    let mut convert = None;
+    let mut header = false;
+    let mut labels = false;
+    let mut missing = Vec::new();
+    let mut warm_start = trout::solver::WarmStart::default();
+    let mut precedences = Vec::new();
+    let mut optional_maps = trout::solver::OptionalMaps::default();
+    let mut start = 0;
+    let mut finish = trout::solver::Finish::default();
+    let mut forced_connections = Vec::new();
+    let mut forbidden_connections = Vec::new();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--threads" | "-j" => {
+                let value = args.next().context("missing value for --threads")?;
+                threads = value
+                    .parse()
+                    .with_context(|| format!("invalid thread count '{value}'"))?;
+            }
+            "--solutions" | "-n" => {
+                let value = args.next().context("missing value for --solutions")?;
+                max_solutions = value
+                    .parse()
+                    .with_context(|| format!("invalid solution count '{value}'"))?;
+                anyhow::ensure!(max_solutions > 0, "expected at least one solution");
+            }
+            "--min-difference" => {
+                let value = args.next().context("missing value for --min-difference")?;
+                min_difference = value
+                    .parse()
+                    .with_context(|| format!("invalid connection count '{value}'"))?;
+            }
+            "--restart-penalty" => {
+                let value = args.next().context("missing value for --restart-penalty")?;
+                let penalty = value
+                    .parse()
+                    .with_context(|| format!("invalid restart penalty '{value}'"))?;
+                restart_penalty = Some(penalty);
+            }
+            "--backend" => {
+                let value = args.next().context("missing value for --backend")?;
+                backend = value.parse()?;
+            }
+            "--lower-bound" => {
+                let value = args.next().context("missing value for --lower-bound")?;
+                lower_bound = value.parse()?;
+            }
+            "--time-limit" => {
+                let value = args.next().context("missing value for --time-limit")?;
+                let seconds = value
+                    .parse()
+                    .with_context(|| format!("invalid time limit '{value}'"))?;
+                let limit = Duration::try_from_secs_f32(seconds)
+                    .with_context(|| format!("invalid time limit '{value}'"))?;
+                time_limit = Some(limit);
+            }
+            "--progress" => progress = true,
+            "--sensitivity" => sensitivity = true,
+            "--force-sensitivity" => force_sensitivity = true,
+            "--suggest-pairs" => suggest_pairs = true,
+            "--rank-drafts" => {
+                let value = args.next().context("missing value for --rank-drafts")?;
+                let improvement = value
+                    .parse()
+                    .with_context(|| format!("invalid improvement '{value}'"))?;
+                rank_drafts = Some(improvement);
+            }
+            "--evaluate" => {
+                let value = args.next().context("missing value for --evaluate")?;
+                evaluate = Some(value);
+            }
+            "--convert" => {
+                let value = args.next().context("missing value for --convert")?;
+                convert = Some(PathBuf::from(value));
+            }
+            "--header" => header = true,
+            "--labels" => labels = true,
+            "--missing" => {
+                let value = args.next().context("missing value for --missing")?;
+                missing.push(value);
+            }
+            "--warm-start" => warm_start.heuristic = true,
+            "--known-route" => {
+                let value = args.next().context("missing value for --known-route")?;
+                let route = value
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|node| !node.is_empty())
+                    .map(|node| node.parse())
+                    .collect::<Result<_, _>>()
+                    .with_context(|| format!("invalid route '{value}'"))?;
+                warm_start.routes.push(route);
+            }
+            "--known-time" => {
+                let value = args.next().context("missing value for --known-time")?;
+                let time = value
+                    .parse()
+                    .with_context(|| format!("invalid time '{value}'"))?;
+                warm_start.time = Some(time);
+            }
+            "--precedence" => {
+                let value = args.next().context("missing value for --precedence")?;
+                precedences.push(value.parse()?);
+            }
+            "--optional" => {
+                let value = args.next().context("missing value for --optional")?;
+                optional_maps.maps.push(value.parse()?);
+            }
+            "--min-optional" => {
+                let value = args.next().context("missing value for --min-optional")?;
+                optional_maps.min_visited = value
+                    .parse()
+                    .with_context(|| format!("invalid optional map count '{value}'"))?;
+            }
+            "--start" => {
+                let value = args.next().context("missing value for --start")?;
+                start = value
+                    .parse()
+                    .with_context(|| format!("invalid start '{value}'"))?;
+            }
+            "--finish" => {
+                let value = args.next().context("missing value for --finish")?;
+                finish = value.parse()?;
+            }
+            "--force" => {
+                let value = args.next().context("missing value for --force")?;
+                forced_connections.extend(trout::solver::Connection::parse_chain(&value)?);
+            }
+            "--forbid" => {
+                let value = args.next().context("missing value for --forbid")?;
+                forbidden_connections.extend(trout::solver::Connection::parse_chain(&value)?);
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    Ok(Args {
+        paths,
+        threads,
+        max_solutions,
+        min_difference,
+        restart_penalty,
+        backend,
+        lower_bound,
+        time_limit,
+        progress,
+        sensitivity,
+        force_sensitivity,
+        rank_drafts,
+        suggest_pairs,
+        evaluate,
+        convert,
+        header,
+        labels,
+        missing,
+        warm_start,
+        precedences,
+        optional_maps,
+        start,
+        finish,
+        forced_connections,
+        forbidden_connections,
+    })
+}
+
+/// how `path` is laid out, if it is a CSV or TSV file
+fn delimited_options(path: &Path, args: &Args) -> Option<trout::table::DelimitedOptions> {
+    let options = match path.extension()?.to_str()? {
+        "csv" => trout::table::DelimitedOptions::csv(),
+        "tsv" => trout::table::DelimitedOptions::tsv(),
+        _ => return None,
+    };
+    Some(trout::table::DelimitedOptions {
+        header: args.header,
+        labels: args.labels,
+        // the first placeholder is written, the defaults can still be read
+        missing: args
+            .missing
+            .iter()
+            .cloned()
+            .chain(options.missing)
+            .collect(),
+        ..options
+    })
+}
+
+fn read_table(path: &Path, args: &Args) -> Result<trout::table::TableInfo> {
+    let table = std::fs::read_to_string(path)?;
+    match delimited_options(path, args) {
+        Some(options) => trout::table::parse_delimited(&table, &options),
+        None => trout::table::parse(&table),
+    }
+    .map_err(|e| anyhow::anyhow!(e.render(&table)))
+    .context("could not parse table")
+}
 
-    let start = std::time::Instant::now();
+fn convert_table(table: &trout::table::TableInfo, output: &Path, args: &Args) -> Result<()> {
+    let converted = match delimited_options(output, args) {
+        Some(options) => table.write_delimited(&options),
+        None if output
+            .extension()
+            .is_some_and(|extension| extension == "edges") =>
+        {
+            table.write_edges()
+        }
+        None if table.names.is_some() => table.write_named(),
+        None => table.write_matrix(),
+    };
+    std::fs::write(output, converted)
+        .with_context(|| format!("could not write {}", output.display()))?;
+    println!("Wrote {}", output.display());
+    Ok(())
+}
 
-    let max_solutions = 12;
-    let mut best_solutions: Vec<(Vec<usize>, u32)> = Vec::new();
+fn solve_table(
+    table_info: &trout::table::TableInfo,
+    args: &Args,
+    cancellation: &CancellationToken,
+) -> Result<()> {
+    let table = &table_info.times;
+
+    let start = Instant::now();
+
+    let max_solutions = args.max_solutions;
+    let mut best_solutions =
+        trout::solver::TopSolutions::diverse(max_solutions, args.min_difference);
 
     #[cfg(feature = "heap_profiling")]
     let _profiler = dhat::Profiler::new_heap();
@@ -19,28 +284,160 @@ fn solve_table(table: &str) -> Result<()> {
     let settings = trout::solver::SolverSettings {
         max_restarts: None,
         only_required_restarts: false,
-        restart_penalty: 190,
+        restart_penalty: args.restart_penalty,
+        backend: args.backend,
+        lower_bound: args.lower_bound,
+        deadline: args.time_limit.map(|time_limit| start + time_limit),
+        cancellation: Some(cancellation.clone()),
+        progress: args.progress.then(|| {
+            trout::solver::ProgressReporter::new(1 << 22, |progress| {
+                let best = progress
+                    .best_time
+                    .map_or_else(|| "-".to_string(), |best| best.to_string());
+                eprint!(
+                    "\r{:5.1}% explored, best {best}, lower bound {}, {} iterations   ",
+                    progress.explored * 100.0,
+                    progress.lower_bound,
+                    progress.iterations,
+                );
+            })
+        }),
+        warm_start: trout::solver::WarmStart {
+            solutions: max_solutions,
+            ..args.warm_start.clone()
+        },
+        precedences: args.precedences.clone(),
+        optional_maps: args.optional_maps.clone(),
+        start: args.start,
+        finish: args.finish.clone(),
+        forced_connections: args.forced_connections.clone(),
+        forbidden_connections: args.forbidden_connections.clone(),
     };
-    let stats = trout::solver::solve_table(
-        &table,
-        &settings,
-        trout::solver::emit_top_n_solutions(&mut best_solutions, max_solutions),
-    );
+    for warning in trout::validate::validate(table, &settings)? {
+        println!("Warning: {}", warning.message(table_info));
+    }
+
+    let stats =
+        trout::solver::solve_table_parallel(table, &settings, args.threads, best_solutions.emit())?;
     let duration = start.elapsed();
+    let best_solutions = best_solutions.into_routes(table, &settings);
+    if args.progress {
+        eprintln!();
+    }
 
-    for (route, time) in best_solutions[0..max_solutions.min(5)].iter().rev() {
-        println!("{:?} - {}", route, time);
+    for route in best_solutions.iter().take(5).rev() {
+        println!("{} - {}", table_info.format_maps(&route.maps), route.time);
+    }
+    if best_solutions.is_empty() {
+        println!("No solution found");
+    }
+    if !stats.complete {
+        match best_solutions.first() {
+            Some(best) => println!(
+                "Search was stopped early, the best route is at most {} frames slower than optimal",
+                best.time - stats.lower_bound.min(best.time)
+            ),
+            None => println!(
+                "Search was stopped early, no route is faster than {}",
+                stats.lower_bound
+            ),
+        }
     }
 
     println!("Routing took {:02}s", duration.as_secs_f32());
+    if let Some(warm_start_bound) = stats.warm_start_bound {
+        println!("Started with a bound of {}", warm_start_bound);
+    }
     println!("{} solutions", stats.solutions_found);
     println!("Pathfind function called {} times.", stats.iterations);
     println!("Branches cut: {}", stats.cut_branches);
+    for bound in trout::solver::LowerBound::ALL {
+        let cut_branches = stats.cut_branches_by(bound);
+        if cut_branches > 0 {
+            println!("  by {:?}: {}", bound, cut_branches);
+        }
+    }
     println!("\n-- Settings used --");
+    println!("Backend: {:?}", settings.backend);
+    println!("Lower Bound: {:?}", settings.lower_bound);
+    println!("Threads: {}", args.threads);
+    if args.min_difference > 0 {
+        println!("Min Difference: {} connections", args.min_difference);
+    }
+    println!("Start: {}", table_info.map_name(settings.start));
+    match &settings.finish {
+        trout::solver::Finish::Last => println!("Finish: {}", table_info.map_name(table.len() - 1)),
+        trout::solver::Finish::Nodes(finishes) => {
+            println!("Finish: {}", table_info.format_maps(finishes))
+        }
+        trout::solver::Finish::Anywhere => println!("Finish: anywhere"),
+    }
+    match settings.restart_penalty {
+        Some(restart_penalty) => println!("Restart Penalty: {}", restart_penalty),
+        None => println!("Restart Penalty: from table"),
+    }
     println!(
         "Only Dead End Restarts: {}",
         settings.only_required_restarts
     );
+    if !settings.precedences.is_empty() {
+        let precedences: Vec<_> = settings
+            .precedences
+            .iter()
+            .map(|precedence| {
+                format!(
+                    "{} before {}",
+                    table_info.map_name(precedence.before),
+                    table_info.map_name(precedence.after)
+                )
+            })
+            .collect();
+        println!("Precedences: {}", precedences.join(", "));
+    }
+    if !settings.optional_maps.maps.is_empty() {
+        let optional_maps: Vec<_> = settings
+            .optional_maps
+            .maps
+            .iter()
+            .map(|optional| {
+                format!(
+                    "{} (skip {})",
+                    table_info.map_name(optional.map),
+                    optional.skip_penalty
+                )
+            })
+            .collect();
+        println!(
+            "Optional Maps: {}, at least {} visited",
+            optional_maps.join(", "),
+            settings.optional_maps.min_visited
+        );
+    }
+    let format_connections = |connections: &[trout::solver::Connection]| {
+        let connections: Vec<_> = connections
+            .iter()
+            .map(|connection| {
+                format!(
+                    "{}-{}",
+                    table_info.map_name(connection.from),
+                    table_info.map_name(connection.to)
+                )
+            })
+            .collect();
+        connections.join(", ")
+    };
+    if !settings.forced_connections.is_empty() {
+        println!(
+            "Forced Connections: {}",
+            format_connections(&settings.forced_connections)
+        );
+    }
+    if !settings.forbidden_connections.is_empty() {
+        println!(
+            "Forbidden Connections: {}",
+            format_connections(&settings.forbidden_connections)
+        );
+    }
     match settings.max_restarts {
         Some(max_restarts) => {
             println!("Max Restart Count: {}", max_restarts)
@@ -50,43 +447,260 @@ fn solve_table(table: &str) -> Result<()> {
         }
     }
 
+    if !stats.complete || best_solutions.is_empty() {
+        return Ok(());
+    }
+
+    if args.sensitivity || args.force_sensitivity {
+        print_sensitivity(table_info, &settings, args.force_sensitivity)?;
+    }
+    if let Some(improvement) = args.rank_drafts {
+        print_improvements(table_info, &settings, improvement, &best_solutions[0])?;
+    }
+
     println!("\n\nPossible new connections:");
-    let suggestion_start = std::time::Instant::now();
+    let suggestion_start = Instant::now();
+
+    // the time limit and progress only apply to the main search
+    let settings = trout::solver::SolverSettings {
+        deadline: None,
+        progress: None,
+        ..settings
+    };
 
-    let best_time = best_solutions.last().unwrap().1;
+    let best_time = best_solutions.last().unwrap().time;
     let frame_difference_threshold = 300;
 
     trout::solver::find_new_connections(
-        &table,
+        table,
         &settings,
         best_time - frame_difference_threshold,
-        |possible_connection| {
+        args.threads,
+        |possible_connection: trout::solver::PossibleConnection| {
+            if args.progress {
+                eprint!("\r");
+            }
             println!(
-                "{: >2}-{: <2}) needs to be {: >3}, from {} ({:?})",
-                possible_connection.start,
-                possible_connection.end,
-                best_time - possible_connection.time,
-                possible_connection.time,
-                possible_connection.path,
+                "{: >2}-{: <2}) needs to be {: >3}, from {} ({})",
+                table_info.map_name(possible_connection.start),
+                table_info.map_name(possible_connection.end),
+                best_time - possible_connection.route.time,
+                possible_connection.route.time,
+                table_info.format_maps(&possible_connection.route.maps),
             );
         },
-    );
+        |tested, total| {
+            if args.progress {
+                eprint!("\r{tested}/{total} connections tested   ");
+            }
+        },
+    )?;
+    if args.progress {
+        eprintln!();
+    }
     let suggestion_duration = suggestion_start.elapsed();
     println!("Suggesting took {:02}s", suggestion_duration.as_secs_f32());
 
+    if args.suggest_pairs {
+        println!("\n\nPossible pairs of new connections:");
+        let suggestion_start = Instant::now();
+
+        trout::solver::find_new_connection_pairs(
+            table,
+            &settings,
+            best_time - frame_difference_threshold,
+            args.threads,
+            |pair: trout::solver::PossibleConnectionPair| {
+                if args.progress {
+                    eprint!("\r");
+                }
+                let alone = pair.single_time.map_or_else(String::new, |single_time| {
+                    format!(", {} more than alone", single_time - pair.route.time)
+                });
+                println!(
+                    "{: >2}-{: <2} + {: >2}-{: <2}) need to be {: >3} together, from {}{alone} ({})",
+                    table_info.map_name(pair.first.from),
+                    table_info.map_name(pair.first.to),
+                    table_info.map_name(pair.second.from),
+                    table_info.map_name(pair.second.to),
+                    best_time - pair.route.time,
+                    pair.route.time,
+                    table_info.format_maps(&pair.route.maps),
+                );
+            },
+            |tested, total| {
+                if args.progress {
+                    eprint!("\r{tested}/{total} connections and pairs tested   ");
+                }
+            },
+        )?;
+        if args.progress {
+            eprintln!();
+        }
+        let suggestion_duration = suggestion_start.elapsed();
+        println!(
+            "Suggesting pairs took {:02}s",
+            suggestion_duration.as_secs_f32()
+        );
+    }
+
+    Ok(())
+}
+
+fn evaluate_route(table_info: &trout::table::TableInfo, route: &str, args: &Args) -> Result<()> {
+    let route = trout::route::parse_route(route, args.start, table_info.names.as_deref())?;
+
+    let settings = trout::solver::SolverSettings {
+        restart_penalty: args.restart_penalty,
+        precedences: args.precedences.clone(),
+        optional_maps: args.optional_maps.clone(),
+        start: args.start,
+        finish: args.finish.clone(),
+        forced_connections: args.forced_connections.clone(),
+        forbidden_connections: args.forbidden_connections.clone(),
+        ..Default::default()
+    };
+    let route = trout::route::evaluate_route(&table_info.times, &settings, &route)
+        .with_context(|| format!("route {} isn't possible", table_info.format_maps(&route)))?;
+
+    for leg in &route.legs {
+        let restart = if leg.restart { " (restart)" } else { "" };
+        println!(
+            "{: >2}-{: <2} {: >5}{restart}",
+            table_info.map_name(leg.from),
+            table_info.map_name(leg.to),
+            leg.time
+        );
+    }
+    println!("Restarts: {}", route.restarts);
+    if route.skip_penalty > 0 {
+        println!("Skipped maps: {}", route.skip_penalty);
+    }
+    println!("{} - {}", table_info.format_maps(&route.maps), route.time);
+    Ok(())
+}
+
+fn print_sensitivity(
+    table_info: &trout::table::TableInfo,
+    settings: &trout::solver::SolverSettings,
+    force: bool,
+) -> Result<()> {
+    use trout::analysis::Sensitivity;
+
+    println!("\n\nSensitivity of the best route:");
+    let maps = table_info.times.len();
+    if maps > trout::analysis::SENSITIVITY_MAX_MAPS && !force {
+        println!(
+            "Skipped, the analysis searches once per connection which takes too long for {maps} maps (at most {}). Use --force-sensitivity to run it anyway.",
+            trout::analysis::SENSITIVITY_MAX_MAPS
+        );
+        return Ok(());
+    }
+    let analysis_start = Instant::now();
+
+    trout::analysis::analyze_sensitivity(&table_info.times, settings, |connection| {
+        let time = connection
+            .time
+            .map_or_else(|| "-".to_string(), |time| time.to_string());
+        let from_to = format!(
+            "{: >2}-{: <2} ({: >4})",
+            table_info.map_name(connection.connection.from),
+            table_info.map_name(connection.connection.to),
+            time
+        );
+        match connection.sensitivity {
+            Sensitivity::Used { slack: Some(slack) } => {
+                println!("{from_to} can get {slack} frames slower")
+            }
+            Sensitivity::Used { slack: None } => println!("{from_to} is used by every route"),
+            Sensitivity::Unused { below } => match connection.time {
+                Some(time) => println!(
+                    "{from_to} would be used below {below}, {} frames faster",
+                    time - below + 1
+                ),
+                None => println!("{from_to} would be used below {below}"),
+            },
+        }
+    })?;
+    let analysis_duration = analysis_start.elapsed();
+    println!("Analysis took {:02}s", analysis_duration.as_secs_f32());
+    Ok(())
+}
+
+fn print_improvements(
+    table_info: &trout::table::TableInfo,
+    settings: &trout::solver::SolverSettings,
+    improvement: u32,
+    best_route: &trout::route::Route,
+) -> Result<()> {
+    println!("\n\nDrafts worth improving by {improvement} frames:");
+    let ranking_start = Instant::now();
+
+    let improvements =
+        trout::analysis::rank_improvements(&table_info.times, settings, improvement)?
+            .unwrap_or_default();
+    let (improvements, unchanged): (Vec<_>, Vec<_>) = improvements
+        .into_iter()
+        .partition(|improvement| improvement.savings > 0);
+    for improvement in &improvements {
+        print!(
+            "{: >2}-{: <2} ({: >4}) saves {: >3}, {}",
+            table_info.map_name(improvement.connection.from),
+            table_info.map_name(improvement.connection.to),
+            improvement.time,
+            improvement.savings,
+            improvement.route.time,
+        );
+        if improvement.route.maps != best_route.maps {
+            print!(" ({})", table_info.format_maps(&improvement.route.maps));
+        }
+        println!();
+    }
+    println!(
+        "{} other drafts wouldn't make the route faster",
+        unchanged.len()
+    );
+    let ranking_duration = ranking_start.elapsed();
+    println!("Ranking took {:02}s", ranking_duration.as_secs_f32());
     Ok(())
 }
 
 fn main() -> Result<()> {
-    let paths: Vec<_> = std::env::args().skip(1).map(PathBuf::from).collect();
-    anyhow::ensure!(paths.len() > 0, "missing argument of path to table");
+    let args = parse_args()?;
+    anyhow::ensure!(!args.paths.is_empty(), "missing argument of path to table");
+
+    if let Some(output) = &args.convert {
+        anyhow::ensure!(args.paths.len() == 1, "--convert takes a single table");
+        let table = read_table(&args.paths[0], &args)?;
+        return convert_table(&table, output, &args);
+    }
 
-    for path in paths {
+    let cancellation = CancellationToken::new();
+    ctrlc::set_handler({
+        let cancellation = cancellation.clone();
+        move || {
+            if cancellation.is_cancelled() {
+                std::process::exit(130);
+            }
+            eprintln!("\nStopping, press Ctrl+C again to exit immediately");
+            cancellation.cancel();
+        }
+    })
+    .context("failed to set Ctrl+C handler")?;
+
+    for path in &args.paths {
         println!("Solving {}...", path.display());
 
-        let table = std::fs::read_to_string(&path)?;
-        solve_table(&table)?;
+        let table = read_table(path, &args)?;
+        match &args.evaluate {
+            Some(route) => evaluate_route(&table, route, &args)?,
+            None => solve_table(&table, &args, &cancellation)?,
+        }
         println!();
+
+        if cancellation.is_cancelled() {
+            break;
+        }
     }
 
     Ok(())
@@ -0,0 +1,474 @@
+//! Routes with the time of each connection, and parsing and checking them against a table,
+//! e.g. ones proposed by another TASer.
+
+use crate::solver::{Connection, Finish, SolverSettings};
+use crate::MISSING;
+use anyhow::{anyhow, bail, ensure, Result};
+use std::fmt;
+
+/// One connection of a route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Leg {
+    pub from: usize,
+    pub to: usize,
+    pub time: u32,
+    /// whether this goes back to the start
+    pub restart: bool,
+}
+
+/// Route through a table, which starts at the `start` of the solver settings.
+///
+/// Displays like the website shows it (`1 11 [R] 5`), without the start and with restarts as `[R]`,
+/// which [`parse_route`] reads back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    /// maps in the order they are visited, including the start and each restart
+    pub maps: Vec<usize>,
+    pub legs: Vec<Leg>,
+    pub restarts: usize,
+    /// time added for the optional maps which are left out
+    pub skip_penalty: u32,
+    pub time: u32,
+}
+
+impl Route {
+    /// Route for a solution of the solver, which visits `maps` in `time`.
+    pub fn new(table: &[Vec<u32>], settings: &SolverSettings, maps: &[usize], time: u32) -> Route {
+        let legs: Vec<Leg> = maps
+            .windows(2)
+            .map(|pair| {
+                let (from, to) = (pair[0], pair[1]);
+                let restart = to == settings.start;
+                let time = match settings.restart_penalty {
+                    Some(restart_penalty) if restart => restart_penalty,
+                    _ => table[from][to],
+                };
+                Leg {
+                    from,
+                    to,
+                    time,
+                    restart,
+                }
+            })
+            .collect();
+        Route::from_legs(maps.to_vec(), legs, time)
+    }
+
+    fn from_legs(maps: Vec<usize>, legs: Vec<Leg>, time: u32) -> Route {
+        let restarts = legs.iter().filter(|leg| leg.restart).count();
+        let skip_penalty = time.saturating_sub(legs.iter().map(|leg| leg.time).sum());
+        Route {
+            maps,
+            legs,
+            restarts,
+            skip_penalty,
+            time,
+        }
+    }
+
+    /// connections between the maps, in the order they are used
+    pub fn connections(&self) -> impl Iterator<Item = Connection> + '_ {
+        self.legs.iter().map(|leg| Connection {
+            from: leg.from,
+            to: leg.to,
+        })
+    }
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, leg) in self.legs.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            if leg.restart {
+                f.write_str("[R]")?;
+            } else {
+                write!(f, "{}", leg.to)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses a route either like the CLI prints it (`[0, 1, 11, 0, 5]`), or like the website shows it (`1 11 [R] 5`),
+/// which leaves out the `start` and marks restarts with `[R]`.
+///
+/// Maps are looked up in `names` first, like [`TableInfo::format_maps`](crate::table::TableInfo::format_maps) prints
+/// them, and otherwise read as their number. In the website's form names can't contain whitespace.
+pub fn parse_route(route: &str, start: usize, names: Option<&[String]>) -> Result<Vec<usize>> {
+    let route = route.trim();
+    let named = |map: &str| names.and_then(|names| names.iter().position(|name| name == map));
+    let parse_map = |map: &str| match named(map) {
+        Some(map) => Ok(map),
+        None => map
+            .parse::<usize>()
+            .map_err(|e| anyhow!("invalid map '{map}' in route: {e}")),
+    };
+
+    if let Some(maps) = route
+        .strip_prefix('[')
+        .and_then(|route| route.strip_suffix(']'))
+    {
+        return maps
+            .split(',')
+            .map(str::trim)
+            .filter(|map| !map.is_empty())
+            .map(parse_map)
+            .collect();
+    }
+
+    std::iter::once(Ok(start))
+        .chain(
+            route
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|map| !map.is_empty())
+                .map(|map| match map {
+                    "[R]" | "R" if named(map).is_none() => Ok(start),
+                    map => parse_map(map),
+                }),
+        )
+        .collect()
+}
+
+/// Checks that `route` is possible in `table` with `settings`, and returns it with the time of each leg.
+///
+/// Every map has to be visited exactly once, except for optional ones, and the route has to respect the restart
+/// limits, precedences and forced or forbidden connections of `settings`.
+pub fn evaluate_route(
+    table: &[Vec<u32>],
+    settings: &SolverSettings,
+    route: &[usize],
+) -> Result<Route> {
+    let n = table.len();
+    settings.check_table(table)?;
+    let start = settings.start;
+
+    ensure!(
+        route.first() == Some(&start),
+        "route has to begin at the start {start}"
+    );
+    if let Some(&map) = route.iter().find(|&&map| map >= n) {
+        bail!("map {map} is outside of the table (0..{n})");
+    }
+
+    let is_forbidden = |from, to| {
+        settings
+            .forbidden_connections
+            .contains(&Connection { from, to })
+    };
+    let forced_next = |from| {
+        settings
+            .forced_connections
+            .iter()
+            .find(|connection| connection.from == from)
+            .map(|connection| connection.to)
+    };
+    let can_visit = |map, visited: &[bool]| {
+        settings.precedences.iter().all(|precedence| {
+            precedence.after != map || precedence.before == start || visited[precedence.before]
+        })
+    };
+    let can_finish_at = |map| match &settings.finish {
+        Finish::Last => map == n - 1,
+        Finish::Nodes(finishes) => finishes.contains(&map),
+        Finish::Anywhere => map != start,
+    };
+    // whether the route could go on from `from` to `map`, and through the forced connections after it
+    let can_continue = |from: usize, map: usize, visited: &[bool]| {
+        if settings
+            .forced_connections
+            .iter()
+            .any(|connection| connection.to == map)
+        {
+            return false;
+        }
+        let mut visited = visited.to_vec();
+        let (mut from, mut map) = (from, Some(map));
+        while let Some(to) = map {
+            if visited[to]
+                || table[from][to] >= MISSING
+                || is_forbidden(from, to)
+                || !can_visit(to, &visited)
+            {
+                return false;
+            }
+            visited[to] = true;
+            (from, map) = (to, forced_next(to));
+        }
+        true
+    };
+
+    let mut visited = vec![false; n];
+    visited[start] = true;
+    let mut restarts = 0;
+    let mut legs = Vec::with_capacity(route.len().saturating_sub(1));
+    for pair in route.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        ensure!(from != to, "map {from} is followed by itself");
+        ensure!(
+            !is_forbidden(from, to),
+            "the connection {from}-{to} is forbidden"
+        );
+        if let Some(next) = forced_next(from) {
+            ensure!(to == next, "map {from} has to be followed by {next}");
+        }
+
+        let restart = to == start;
+        let time = if restart {
+            restarts += 1;
+            if let Some(max_restarts) = settings.max_restarts {
+                ensure!(
+                    restarts <= max_restarts,
+                    "the route restarts more than {max_restarts} times"
+                );
+            }
+            if settings.only_required_restarts {
+                // with several finishes, the route could end at `from` instead
+                let dead_end = !(settings.finish.single(n).is_none() && can_finish_at(from))
+                    && (0..n).all(|other| other == start || !can_continue(from, other, &visited));
+                ensure!(dead_end, "map {from} isn't a dead end, so it can't restart");
+            }
+            match settings.restart_penalty {
+                Some(restart_penalty) => restart_penalty,
+                None => table[from][start],
+            }
+        } else {
+            ensure!(!visited[to], "map {to} is visited more than once");
+            ensure!(
+                can_visit(to, &visited),
+                "map {to} is visited before the maps which have to come first"
+            );
+            visited[to] = true;
+            table[from][to]
+        };
+        ensure!(time < MISSING, "there is no connection from {from} to {to}");
+
+        legs.push(Leg {
+            from,
+            to,
+            time,
+            restart,
+        });
+    }
+
+    let last = *route.last().unwrap();
+    ensure!(can_finish_at(last), "the route can't finish at map {last}");
+    if let Some(next) = forced_next(last) {
+        bail!("map {last} has to be followed by {next}");
+    }
+
+    let optional_maps = &settings.optional_maps;
+    let is_optional = |map| {
+        optional_maps
+            .maps
+            .iter()
+            .any(|optional| optional.map == map)
+    };
+    if let Some(map) = (0..n).find(|&map| !visited[map] && !is_optional(map)) {
+        bail!("map {map} isn't visited");
+    }
+    let optional_visits = optional_maps
+        .maps
+        .iter()
+        .filter(|optional| visited[optional.map])
+        .count();
+    ensure!(
+        optional_visits >= optional_maps.min_visited,
+        "only {optional_visits} of at least {} optional maps are visited",
+        optional_maps.min_visited
+    );
+    let skip_penalty: u32 = optional_maps
+        .maps
+        .iter()
+        .filter(|optional| !visited[optional.map])
+        .map(|optional| optional.skip_penalty)
+        .sum();
+
+    let time = legs.iter().map(|leg| leg.time).sum::<u32>() + skip_penalty;
+    Ok(Route::from_legs(route.to_vec(), legs, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{emit_top_n_solutions, solve_table};
+    use crate::testing::{random_table, settings_variants, Rng};
+
+    fn table() -> Vec<Vec<u32>> {
+        vec![
+            vec![0, 10, 20, MISSING],
+            vec![5, 0, 7, 30],
+            vec![6, 8, 0, 9],
+            vec![0, 0, 0, 0],
+        ]
+    }
+
+    fn evaluate(route: &str, settings: &SolverSettings) -> Result<Route> {
+        evaluate_route(
+            &table(),
+            settings,
+            &parse_route(route, settings.start, None)?,
+        )
+    }
+
+    #[test]
+    fn parses_the_cli_and_website_forms() {
+        assert_eq!(
+            parse_route("[0, 1, 0, 2, 3]", 0, None).unwrap(),
+            [0, 1, 0, 2, 3]
+        );
+        assert_eq!(
+            parse_route(" 1 [R] 2,3 ", 0, None).unwrap(),
+            [0, 1, 0, 2, 3]
+        );
+        assert_eq!(parse_route("1 R 3", 2, None).unwrap(), [2, 1, 2, 3]);
+        assert!(parse_route("1 x 3", 0, None).is_err());
+        assert!(parse_route("[0, -1]", 0, None).is_err());
+    }
+
+    #[test]
+    fn evaluates_each_leg() {
+        let settings = SolverSettings::default();
+        let route = evaluate("1 [R] 2 3", &settings).unwrap();
+        assert_eq!(route.time, 10 + 5 + 20 + 9);
+        assert_eq!(route.restarts, 1);
+        assert_eq!(
+            route.legs.iter().map(|leg| leg.time).collect::<Vec<_>>(),
+            [10, 5, 20, 9]
+        );
+        assert!(route.legs[1].restart && !route.legs[2].restart);
+
+        let settings = SolverSettings {
+            restart_penalty: Some(100),
+            ..SolverSettings::default()
+        };
+        assert_eq!(
+            evaluate("1 [R] 2 3", &settings).unwrap().time,
+            10 + 100 + 20 + 9
+        );
+    }
+
+    #[test]
+    fn rejects_impossible_routes() {
+        let settings = SolverSettings::default();
+        let error = |route: &str, settings: &SolverSettings| {
+            evaluate(route, settings).unwrap_err().to_string()
+        };
+
+        assert_eq!(
+            error("[1, 2, 3]", &settings),
+            "route has to begin at the start 0"
+        );
+        assert_eq!(
+            error("1 2 4", &settings),
+            "map 4 is outside of the table (0..4)"
+        );
+        assert_eq!(error("1 1 2 3", &settings), "map 1 is followed by itself");
+        assert_eq!(
+            error("1 2 1 3", &settings),
+            "map 1 is visited more than once"
+        );
+        assert_eq!(error("1 3", &settings), "map 2 isn't visited");
+        assert_eq!(error("2 1", &settings), "the route can't finish at map 1");
+        assert_eq!(
+            error("1 2 [R] 3", &settings),
+            "there is no connection from 0 to 3"
+        );
+
+        let settings = SolverSettings {
+            max_restarts: Some(0),
+            ..SolverSettings::default()
+        };
+        assert_eq!(
+            error("1 [R] 2 3", &settings),
+            "the route restarts more than 0 times"
+        );
+    }
+
+    #[test]
+    fn routes_of_solutions_match_their_evaluation() {
+        let mut rng = Rng::new(20);
+        let mut restarts = 0;
+        for case in 0..30 {
+            let n = 4 + case % 3;
+            let table = random_table(&mut rng, n, 30);
+            for settings in settings_variants(n) {
+                let mut solutions = Vec::new();
+                solve_table(&table, &settings, emit_top_n_solutions(&mut solutions, 3)).unwrap();
+                for (maps, time) in solutions {
+                    let route = Route::new(&table, &settings, &maps, time);
+                    assert_eq!(route.time, time);
+                    assert_eq!(route.legs.len(), maps.len() - 1);
+                    assert!(route
+                        .connections()
+                        .zip(maps.windows(2))
+                        .all(|(connection, pair)| [connection.from, connection.to] == pair));
+                    assert_eq!(
+                        route.restarts,
+                        maps[1..]
+                            .iter()
+                            .filter(|&&map| map == settings.start)
+                            .count()
+                    );
+                    restarts += route.restarts;
+
+                    let parsed = parse_route(&route.to_string(), settings.start, None).unwrap();
+                    assert_eq!(parsed, maps);
+                    assert_eq!(
+                        evaluate_route(&table, &settings, &parsed).unwrap(),
+                        route,
+                        "{table:?} with {settings:?}"
+                    );
+                }
+            }
+        }
+        assert!(restarts > 0);
+    }
+
+    #[test]
+    fn reads_back_routes_printed_with_names() {
+        let table_info = crate::table::parse(
+            "map    restart  1-A  1-B  R    2-A
+             start  0        169  -    -    -
+             1-A    190      0    257  80   -
+             1-B    190      330  0    -    400
+             R      150      -    90   0    -
+             2-A    0        0    0    0    0",
+        )
+        .unwrap();
+        let names = table_info.names.as_deref();
+        let settings = SolverSettings::default();
+
+        let mut best = None;
+        solve_table(
+            &table_info.times,
+            &settings,
+            crate::solver::emit_only_best(&mut best),
+        )
+        .unwrap();
+        let (maps, time) = best.unwrap();
+        let printed = table_info.format_maps(&maps);
+        assert_eq!(printed, "[start, 1-A, R, 1-B, 2-A]");
+        let route = evaluate_route(
+            &table_info.times,
+            &settings,
+            &parse_route(&printed, 0, names).unwrap(),
+        )
+        .unwrap();
+        assert_eq!((route.maps, route.time), (maps, time));
+
+        // like the website shows it, where the map named R isn't a restart
+        assert_eq!(
+            parse_route("1-A R 1-B [R] 3", 0, names).unwrap(),
+            [0, 1, 3, 2, 0, 3]
+        );
+        assert!(parse_route("1-A 1-C", 0, names).is_err());
+
+        // maps outside of the table are reported by their number, like the CLI does when evaluating fails
+        let maps = parse_route("1-A 99", 0, names).unwrap();
+        assert_eq!(table_info.format_maps(&maps), "[start, 1-A, 99]");
+        let error = evaluate_route(&table_info.times, &settings, &maps).unwrap_err();
+        assert_eq!(error.to_string(), "map 99 is outside of the table (0..5)");
+    }
+}
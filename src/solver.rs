@@ -1,5 +1,15 @@
 // code taking and adapted from https://github.com/TheRoboManTAS/Celeste-TAS-lobby-router/, credit goes to @TheRoboManTAS
 
+mod bounds;
+mod held_karp;
+mod numbering;
+mod warm_start;
+
+use crate::route::Route;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
 type Time = u32;
 type NodeIdx = usize;
 
@@ -31,167 +41,1366 @@ impl PlaceInfo {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct SolverSettings {
     pub max_restarts: Option<u32>,
     pub only_required_restarts: bool,
-    pub restart_penalty: Time,
+    /// Time it takes to restart from any map, instead of the restart time of each map.
+    /// In tables the restart times are the column of the start, which is the first one by default.
+    pub restart_penalty: Option<Time>,
+    pub backend: Backend,
+    pub lower_bound: LowerBound,
+    /// stop the search once this point in time is reached
+    pub deadline: Option<Instant>,
+    /// stop the search once this token is cancelled
+    pub cancellation: Option<CancellationToken>,
+    /// gets called periodically while the search is running
+    pub progress: Option<ProgressReporter>,
+    pub warm_start: WarmStart,
+    /// maps which have to be visited before others
+    pub precedences: Vec<Precedence>,
+    /// maps which may be left out of the route
+    pub optional_maps: OptionalMaps,
+    /// map every route starts at and restarts go back to
+    pub start: NodeIdx,
+    pub finish: Finish,
+    /// connections which have to be taken whenever their first map is visited, e.g. a committed sub-route
+    pub forced_connections: Vec<Connection>,
+    /// connections which can't be used, as if the table didn't contain them
+    pub forbidden_connections: Vec<Connection>,
+}
+
+impl SolverSettings {
+    /// Checks that the maps the settings refer to are part of a table with `n` nodes,
+    /// and that the settings aren't impossible on their own. Solving returns the same error if this fails.
+    pub fn check(&self, n: usize) -> Result<(), SettingsError> {
+        macro_rules! ensure {
+            ($condition:expr, $($message:tt)+) => {
+                if !$condition {
+                    return Err(SettingsError(format!($($message)+)));
+                }
+            };
+        }
+
+        let start = self.start;
+        ensure!(start < n, "start {start} is outside of the table (0..{n})");
+        match &self.finish {
+            Finish::Last => ensure!(start != n - 1, "the start can't be the finish"),
+            Finish::Nodes(finishes) => {
+                ensure!(!finishes.is_empty(), "expected at least one finish");
+                for &finish in finishes {
+                    ensure!(
+                        finish < n,
+                        "finish {finish} is outside of the table (0..{n})"
+                    );
+                    ensure!(finish != start, "the start can't be a finish");
+                }
+            }
+            Finish::Anywhere => {}
+        }
+        // with several finishes, each of them may be visited before finishing at another one
+        let finish = self.finish.single(n);
+
+        for &Precedence { before, after } in &self.precedences {
+            ensure!(
+                before < n && after < n,
+                "precedence {before}:{after} refers to a map outside of the table (0..{n})"
+            );
+            ensure!(
+                before != after,
+                "map {before} can't be visited before itself"
+            );
+            ensure!(after != start, "nothing can be visited before the start");
+            ensure!(
+                Some(before) != finish,
+                "nothing can be visited after the finish"
+            );
+        }
+
+        let optional_maps = &self.optional_maps;
+        for (i, optional) in optional_maps.maps.iter().enumerate() {
+            let map = optional.map;
+            ensure!(
+                map < n,
+                "optional map {map} is outside of the table (0..{n})"
+            );
+            ensure!(
+                map != start && Some(map) != finish,
+                "map {map} can't be optional, the start and finish are always part of the route"
+            );
+            ensure!(
+                optional_maps.maps[..i].iter().all(|other| other.map != map),
+                "map {map} is optional more than once"
+            );
+        }
+        ensure!(
+            optional_maps.min_visited <= optional_maps.maps.len(),
+            "can't visit {} of only {} optional maps",
+            optional_maps.min_visited,
+            optional_maps.maps.len()
+        );
+
+        for &Connection { from, to } in &self.forbidden_connections {
+            ensure!(
+                from < n && to < n,
+                "forbidden connection {from}-{to} refers to a map outside of the table (0..{n})"
+            );
+        }
+
+        let mut next = vec![None; n];
+        let mut has_previous = vec![false; n];
+        for &connection @ Connection { from, to } in &self.forced_connections {
+            ensure!(
+                from < n && to < n,
+                "forced connection {from}-{to} refers to a map outside of the table (0..{n})"
+            );
+            ensure!(from != to, "map {from} can't be connected to itself");
+            ensure!(
+                from != start && to != start,
+                "connections from or to the start can't be forced"
+            );
+            ensure!(
+                Some(from) != finish,
+                "nothing can be visited after the finish"
+            );
+            ensure!(
+                !self.forbidden_connections.contains(&connection),
+                "connection {from}-{to} is both forced and forbidden"
+            );
+            ensure!(
+                !optional_maps
+                    .maps
+                    .iter()
+                    .any(|optional| optional.map == from || optional.map == to),
+                "forced connection {from}-{to} can't contain optional maps"
+            );
+            ensure!(
+                next[from].is_none_or(|next| next == to),
+                "map {from} has more than one forced connection leaving it"
+            );
+            ensure!(
+                next[from].is_some() || !has_previous[to],
+                "map {to} has more than one forced connection going to it"
+            );
+            next[from] = Some(to);
+            has_previous[to] = true;
+        }
+        for node in 0..n {
+            let mut chain = std::iter::successors(next[node], |&node| next[node]).take(n);
+            ensure!(
+                !chain.any(|other| other == node),
+                "forced connections form a cycle through map {node}"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Like [`SolverSettings::check`], and checks that `table` has as many columns as rows.
+    pub fn check_table(&self, table: &[Vec<u32>]) -> Result<(), SettingsError> {
+        let n = table.len();
+        if let Some((map, row)) = table.iter().enumerate().find(|(_, row)| row.len() != n) {
+            return Err(SettingsError(format!(
+                "row {map} has {} columns, but the table has {n} rows",
+                row.len()
+            )));
+        }
+        self.check(n)
+    }
+
+    fn should_stop(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+            || self
+                .cancellation
+                .as_ref()
+                .is_some_and(CancellationToken::is_cancelled)
+    }
+}
+
+/// Settings which can't be used with a table, or a table which isn't square, see [`SolverSettings::check_table`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingsError(String);
+
+impl std::fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+/// Number of iterations between checks whether the search should stop
+const STOP_CHECK_INTERVAL: u32 = 1 << 14;
+
+/// Work of the branch and bound search between checks whether it should stop, a few milliseconds.
+/// Iterations with the expensive bounds do much more work, so they check more often, see
+/// [`LowerBound::iteration_work`].
+const STOP_CHECK_WORK: u32 = 1 << 22;
+
+/// Handle to stop a running search early. Clones share the same state.
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    check: Option<Arc<dyn Fn() -> bool + Send + Sync>>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// token which is additionally cancelled whenever `check` returns true,
+    /// e.g. for time limits on platforms without [`Instant`]
+    pub fn from_fn(check: impl Fn() -> bool + Send + Sync + 'static) -> Self {
+        CancellationToken {
+            cancelled: Arc::default(),
+            check: Some(Arc::new(check)),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed) || self.check.as_ref().is_some_and(|check| check())
+    }
+}
+
+impl std::fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
+
+/// Constraint that the map `before` has to be visited before the map `after`, e.g. for gates or required cutscenes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Precedence {
+    pub before: NodeIdx,
+    pub after: NodeIdx,
+}
+
+/// parses `before:after`, e.g. `3:7` if map 3 has to be visited before map 7
+impl std::str::FromStr for Precedence {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (before, after) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("expected precedence like '3:7', got '{s}'"))?;
+        let parse = |node: &str| {
+            node.trim()
+                .parse()
+                .map_err(|e| anyhow::anyhow!("invalid map '{node}' in precedence '{s}': {e}"))
+        };
+        Ok(Precedence {
+            before: parse(before)?,
+            after: parse(after)?,
+        })
+    }
+}
+
+/// Maps which don't have to be part of the route, e.g. to route "any k of these maps" or maps which can be skipped at a cost.
+///
+/// Precedences still apply, so a map which has to be visited after a skipped map can't be visited at all.
+#[derive(Debug, Clone, Default)]
+pub struct OptionalMaps {
+    pub maps: Vec<OptionalMap>,
+    /// how many of the optional maps have to be visited at least
+    pub min_visited: usize,
+}
+
+/// A map which may be left out of the route, which adds `skip_penalty` to the time of the route
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptionalMap {
+    pub map: NodeIdx,
+    pub skip_penalty: Time,
+}
+
+/// parses `map` or `map:skip_penalty`, e.g. `5:300` if skipping map 5 costs 300 frames
+impl std::str::FromStr for OptionalMap {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (map, skip_penalty) = s.split_once(':').unwrap_or((s, "0"));
+        Ok(OptionalMap {
+            map: map
+                .trim()
+                .parse()
+                .map_err(|e| anyhow::anyhow!("invalid map '{map}' in optional map '{s}': {e}"))?,
+            skip_penalty: skip_penalty.trim().parse().map_err(|e| {
+                anyhow::anyhow!("invalid skip penalty '{skip_penalty}' in optional map '{s}': {e}")
+            })?,
+        })
+    }
+}
+
+/// Connection from one map to another, parsed from `from-to`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Connection {
+    pub from: NodeIdx,
+    pub to: NodeIdx,
+}
+
+impl Connection {
+    /// parses a chain like `4-5-6` into the connections `4-5` and `5-6`
+    pub fn parse_chain(chain: &str) -> anyhow::Result<Vec<Connection>> {
+        let nodes = chain
+            .split('-')
+            .map(|node| {
+                node.trim().parse().map_err(|e| {
+                    anyhow::anyhow!("invalid map '{node}' in connection '{chain}': {e}")
+                })
+            })
+            .collect::<anyhow::Result<Vec<NodeIdx>>>()?;
+        anyhow::ensure!(
+            nodes.len() >= 2,
+            "expected a connection like '4-5' or a chain like '4-5-6', got '{chain}'"
+        );
+        Ok(nodes
+            .windows(2)
+            .map(|pair| Connection {
+                from: pair[0],
+                to: pair[1],
+            })
+            .collect())
+    }
+}
+
+/// Where routes may end
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Finish {
+    /// the last map of the table
+    #[default]
+    Last,
+    /// any of these maps, after visiting the other ones
+    Nodes(Vec<NodeIdx>),
+    /// any map, e.g. for routes through part of a lobby
+    Anywhere,
+}
+
+impl Finish {
+    /// the only map routes may end at in a table with `n` nodes, if there is only one
+    pub(crate) fn single(&self, n: usize) -> Option<NodeIdx> {
+        match self {
+            Finish::Last => Some(n - 1),
+            Finish::Nodes(finishes) => match finishes[..] {
+                [finish] => Some(finish),
+                _ => None,
+            },
+            Finish::Anywhere => None,
+        }
+    }
 }
 
+/// parses `last`, `anywhere` or a list of maps like `20,22`
+impl std::str::FromStr for Finish {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "last" => Ok(Finish::Last),
+            "anywhere" => Ok(Finish::Anywhere),
+            list => list
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|node| !node.is_empty())
+                .map(|node| {
+                    node.parse().map_err(|e| {
+                        anyhow::anyhow!(
+                            "invalid finish '{node}', expected 'last', 'anywhere' or maps like '20,22': {e}"
+                        )
+                    })
+                })
+                .collect::<Result<_, _>>()
+                .map(Finish::Nodes),
+        }
+    }
+}
+
+/// Known routes used to start the branch and bound search with a pruning bound, instead of searching without one until the first solutions are found.
+///
+/// The known routes are passed to `emit_solution` before the search starts, so the bound is the worst interesting time
+/// it returns for them. That keeps the search exact for every collector, including ones which don't simply keep the
+/// fastest routes, like [`TopSolutions::diverse`].
+#[derive(Debug, Clone)]
+pub struct WarmStart {
+    /// look for good routes using nearest neighbour and local search before the exact search
+    pub heuristic: bool,
+    /// routes from an earlier session, like `[0, 3, 1, 0, 2, 4]`. Impossible routes are ignored.
+    pub routes: Vec<Vec<NodeIdx>>,
+    /// Time of a route from an earlier session. Only routes at most this slow are searched for,
+    /// so with several solutions fewer than wanted may be found.
+    pub time: Option<Time>,
+    /// amount of known routes passed to `emit_solution`, e.g. `max_solutions` of [`emit_top_n_solutions`]
+    pub solutions: usize,
+}
+
+impl Default for WarmStart {
+    fn default() -> Self {
+        WarmStart {
+            heuristic: false,
+            routes: Vec::new(),
+            time: None,
+            solutions: 1,
+        }
+    }
+}
+
+/// Snapshot of a running search, see [`ProgressReporter`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Progress {
+    pub iterations: u32,
+    pub cut_branches: u32,
+    /// time of the fastest route found so far
+    pub best_time: Option<Time>,
+    /// no route is faster than this, it approaches the best time as the search goes on
+    pub lower_bound: Time,
+    /// rough estimate between 0 and 1 of how much of the search tree is explored,
+    /// based on which branch is taken at the first levels of the tree
+    pub explored: f64,
+}
+
+/// Callback which gets called with the current [`Progress`] every `interval` iterations,
+/// and once more with everything explored when the search completes.
+/// Calls are serialized, even when the search runs on multiple threads.
+///
+/// The held-karp backend reports while filling its table instead, with only the explored fraction set.
+#[derive(Clone)]
+pub struct ProgressReporter {
+    interval: u32,
+    report: Arc<dyn Fn(&Progress) + Send + Sync>,
+}
+
+impl ProgressReporter {
+    pub fn new(interval: u32, report: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        ProgressReporter {
+            interval: interval.max(1),
+            report: Arc::new(report),
+        }
+    }
+
+    fn report(&self, progress: &Progress) {
+        (self.report)(progress)
+    }
+}
+
+impl std::fmt::Debug for ProgressReporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressReporter")
+            .field("interval", &self.interval)
+            .finish()
+    }
+}
+
+/// Algorithm used to search for solutions
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// depth first search, pruned by a lower bound of the remaining time
+    #[default]
+    BranchAndBound,
+    /// exact dynamic programming over every set of visited maps.
+    /// Has a predictable runtime for lobbies of up to ~22 maps, larger lobbies fall back to [`Backend::BranchAndBound`].
+    HeldKarp,
+}
+
+impl std::str::FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "branch-and-bound" | "bnb" => Ok(Backend::BranchAndBound),
+            "held-karp" | "dp" => Ok(Backend::HeldKarp),
+            _ => Err(anyhow::anyhow!(
+                "unknown backend '{s}', expected 'branch-and-bound' or 'held-karp'"
+            )),
+        }
+    }
+}
+
+/// Lower bound of the remaining time, used for pruning the branch and bound search.
+///
+/// The cheap [`LowerBound::CheapestIncoming`] bound is always checked first, the configured bound only if that didn't cut the branch.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LowerBound {
+    /// sum of the cheapest incoming connection of every unvisited node
+    #[default]
+    CheapestIncoming,
+    /// sum of the cheapest incoming connection of every unvisited node, only counting connections from nodes which can still be left
+    AvailableIncoming,
+    /// maximum of the available incoming and outgoing connection sums
+    MaxInOut,
+    /// cheapest assignment of a distinct successor to every node which still has to be left
+    Assignment,
+}
+
+impl LowerBound {
+    pub const ALL: [LowerBound; 4] = [
+        LowerBound::CheapestIncoming,
+        LowerBound::AvailableIncoming,
+        LowerBound::MaxInOut,
+        LowerBound::Assignment,
+    ];
+
+    /// Rough work of an iteration of the search with this bound near the start of the route, in a table with `n` maps,
+    /// where every map is left to visit. About a nanosecond each.
+    fn iteration_work(self, n: usize) -> u32 {
+        let n = n as u64;
+        let work = match self {
+            LowerBound::CheapestIncoming => n * n / 16,
+            LowerBound::AvailableIncoming => n * n,
+            LowerBound::MaxInOut => n * n * n / 32,
+            LowerBound::Assignment => n * n * n / 4,
+        };
+        work.clamp(1, u32::MAX as u64) as u32
+    }
+}
+
+impl std::str::FromStr for LowerBound {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cheapest-incoming" => Ok(LowerBound::CheapestIncoming),
+            "available-incoming" => Ok(LowerBound::AvailableIncoming),
+            "max-in-out" => Ok(LowerBound::MaxInOut),
+            "assignment" => Ok(LowerBound::Assignment),
+            _ => Err(anyhow::anyhow!(
+                "unknown lower bound '{s}', expected one of 'cheapest-incoming', 'available-incoming', 'max-in-out' or 'assignment'"
+            )),
+        }
+    }
+}
+
+/// Counters of a search.
+///
+/// A table with a map which no connection leads to has no route, and its search stops right at the start with
+/// 1 iteration and 1 branch cut by [`LowerBound::CheapestIncoming`], whatever the lower bound is.
+#[derive(Debug, Default, Clone, Copy)]
 pub struct Stats {
     pub iterations: u32,
     pub solutions_found: u32,
     pub cut_branches: u32,
+    /// branches cut by each [`LowerBound`], indexed by `bound as usize`
+    pub cut_branches_by_bound: [u32; LowerBound::ALL.len()],
+    /// whether the whole search tree was explored, `false` if it was stopped early
+    pub complete: bool,
+    /// no route that wasn't emitted is faster than this.
+    /// If the search was stopped early, the gap between this and the best emitted time is the most that could still be gained.
+    pub lower_bound: Time,
+    /// bound the search started with, from the [`WarmStart`]
+    pub warm_start_bound: Option<Time>,
+}
+
+impl Stats {
+    pub fn cut_branches_by(&self, bound: LowerBound) -> u32 {
+        self.cut_branches_by_bound[bound as usize]
+    }
+}
+
+impl std::ops::AddAssign for Stats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.iterations += rhs.iterations;
+        self.solutions_found += rhs.solutions_found;
+        self.cut_branches += rhs.cut_branches;
+        for (cuts, rhs_cuts) in self
+            .cut_branches_by_bound
+            .iter_mut()
+            .zip(rhs.cut_branches_by_bound)
+        {
+            *cuts += rhs_cuts;
+        }
+        self.complete &= rhs.complete;
+        self.lower_bound = self.lower_bound.min(rhs.lower_bound);
+        self.warm_start_bound = match (self.warm_start_bound, rhs.warm_start_bound) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
 }
 
 /// `emit_solution` gets called for each new solution, and returns the worst new interesting time
-pub fn solve_table<F>(table: &[Vec<u32>], settings: &SolverSettings, emit_solution: F) -> Stats
+pub fn solve_table<F>(
+    table: &[Vec<u32>],
+    settings: &SolverSettings,
+    emit_solution: F,
+) -> Result<Stats, SettingsError>
 where
     F: FnMut(&[NodeIdx], Time) -> Time,
 {
+    settings.check_table(table)?;
     let files: Vec<FileInfo> = collect_files(table);
-    solve_files(&files, settings, emit_solution)
+    solve_files(&files, table.len(), settings, emit_solution)
 }
 
-/// `emit_solution` gets called for each new solution, and returns the worst new interesting time
-pub fn solve_files<F>(files: &[FileInfo], settings: &SolverSettings, emit_solution: F) -> Stats
+/// Like [`solve_table`], with the `files` of a table with `n` maps
+pub fn solve_files<F>(
+    files: &[FileInfo],
+    n: usize,
+    settings: &SolverSettings,
+    emit_solution: F,
+) -> Result<Stats, SettingsError>
 where
     F: FnMut(&[NodeIdx], Time) -> Time,
 {
-    let n = files
-        .iter()
-        .map(|file| file.start.max(file.end))
-        .max()
-        .expect("empty files passed")
-        + 1;
+    let problem = Problem::new(files, n, settings)?;
+    Ok(solve_problem(&problem, settings, emit_solution))
+}
 
-    let nodes: Vec<PlaceInfo> = collect_nodes(n, &files);
+fn solve_problem<F>(problem: &Problem, settings: &SolverSettings, mut emit_solution: F) -> Stats
+where
+    F: FnMut(&[NodeIdx], Time) -> Time,
+{
+    if settings.backend == Backend::HeldKarp && held_karp::table_size(problem, settings).is_some() {
+        return held_karp::solve(problem, settings, emit_solution);
+    }
 
-    let start = 0;
-    let finish = n - 1;
+    let (bound, time_bound) = emit_known_routes(problem, settings, &mut emit_solution);
+    let mut cx = SolverContext::new(problem, settings, emit_solution);
+    cx.time_bound = time_bound;
+    cx.worst_time_of_interest = bound;
+    if let Some(progress) = &settings.progress {
+        cx.report_progress = Some(Box::new(|p: &Progress| progress.report(p)));
+    }
+    cx.path_find(problem.start);
 
-    let lowest_times = collect_lowest_times(n, &nodes);
+    let stats = cx.stats();
+    report_finished(settings, &stats, cx.best_time);
+    Stats {
+        warm_start_bound: (bound != Time::MAX).then_some(bound),
+        ..stats
+    }
+}
 
-    let global_lower_bound: Time = lowest_times.iter().sum();
+/// Reports the end of a complete search, which explored everything and found the fastest route
+fn report_finished(settings: &SolverSettings, stats: &Stats, best_time: Option<Time>) {
+    if let Some(progress) = settings.progress.as_ref().filter(|_| stats.complete) {
+        progress.report(&Progress {
+            iterations: stats.iterations,
+            cut_branches: stats.cut_branches,
+            best_time,
+            lower_bound: best_time.unwrap_or(stats.lower_bound),
+            explored: 1.0,
+        });
+    }
+}
 
-    let mut cx = SolverContext {
-        settings,
-        n,
-        local_lower_bound: global_lower_bound,
-        cut_branches: 0,
-        lowest_times,
-        worst_time_of_interest: std::u32::MAX,
-        solutions_found: 0,
-        emit_solution,
-        start,
-        finish,
-        iterations: 0,
-        restart_count: 0,
-        can_go: vec![true; n],
-        trail: vec![0; n + nodes[start].targets.len()],
-        index: 0,
-        visit_count: 0,
-        nodes: &nodes,
-    };
-    cx.path_find(start);
+/// Emits the known routes of the [`WarmStart`], and returns the bound the search starts with,
+/// together with the bound from the known time which the worst time of interest never goes above.
+fn emit_known_routes<F>(
+    problem: &Problem,
+    settings: &SolverSettings,
+    emit_solution: &mut F,
+) -> (Time, Time)
+where
+    F: FnMut(&[NodeIdx], Time) -> Time,
+{
+    let time_bound = warm_start::time_bound(settings).unwrap_or(Time::MAX);
+    let mut bound = time_bound;
+    for (trail, time) in warm_start::known_routes(problem, settings) {
+        if time >= time_bound {
+            continue;
+        }
+        let route = problem.numbering.to_original(&trail);
+        bound = emit_solution(&route, time).min(time_bound);
+    }
+    (bound, time_bound)
+}
+
+/// Like [`solve_table`], but runs the search on `threads` worker threads
+pub fn solve_table_parallel<F>(
+    table: &[Vec<u32>],
+    settings: &SolverSettings,
+    threads: usize,
+    emit_solution: F,
+) -> Result<Stats, SettingsError>
+where
+    F: FnMut(&[NodeIdx], Time) -> Time + Send,
+{
+    settings.check_table(table)?;
+    let files: Vec<FileInfo> = collect_files(table);
+    solve_files_parallel(&files, table.len(), settings, threads, emit_solution)
+}
+
+/// Like [`solve_files`], but splits the search tree by route prefix and runs it on `threads` worker threads.
+///
+/// The worst interesting time is shared between the workers, so a solution found in one thread prunes all of them.
+/// Calls to `emit_solution` are serialized, but arrive in a nondeterministic order.
+pub fn solve_files_parallel<F>(
+    files: &[FileInfo],
+    n: usize,
+    settings: &SolverSettings,
+    threads: usize,
+    mut emit_solution: F,
+) -> Result<Stats, SettingsError>
+where
+    F: FnMut(&[NodeIdx], Time) -> Time + Send,
+{
+    if threads <= 1 {
+        return solve_files(files, n, settings, emit_solution);
+    }
+
+    let problem = Problem::new(files, n, settings)?;
+
+    if settings.backend == Backend::HeldKarp && held_karp::table_size(&problem, settings).is_some()
+    {
+        return Ok(held_karp::solve(&problem, settings, emit_solution));
+    }
 
-    let stats = Stats {
-        solutions_found: cx.solutions_found,
-        iterations: cx.iterations,
-        cut_branches: cx.cut_branches,
+    // split the tree deep enough that every worker has a few prefixes to pick from.
+    // no route is shorter than the split depth, so the split pass never finds solutions itself.
+    let min_prefixes = threads * 8;
+    let shortest_route = problem.shortest_route_len();
+    let mut depth = 1;
+    let (prefixes, mut stats) = loop {
+        let mut cx = SolverContext::new(&problem, settings, |_: &[NodeIdx], _| u32::MAX);
+        cx.split_depth = Some(depth);
+        cx.path_find(problem.start);
+
+        if cx.prefixes.len() >= min_prefixes || depth >= shortest_route || cx.stopped {
+            break (std::mem::take(&mut cx.prefixes), cx.stats());
+        }
+        depth += 1;
     };
 
-    stats
+    let (bound, time_bound) = emit_known_routes(&problem, settings, &mut emit_solution);
+    stats.warm_start_bound = (bound != Time::MAX).then_some(bound);
+
+    let emit_solution = Mutex::new(emit_solution);
+    let worst_time_of_interest = AtomicU32::new(bound);
+    let best_time = AtomicU32::new(u32::MAX);
+    let shared = Mutex::new(SharedProgress {
+        next_prefix: 0,
+        finished_prefixes: 0,
+        workers: vec![
+            Progress {
+                lower_bound: Time::MAX,
+                ..Default::default()
+            };
+            threads
+        ],
+    });
+
+    // lowest bound of all prefixes starting at each index, for the lower bound of the prefixes nobody started yet
+    let mut suffix_lower_bounds = vec![Time::MAX; prefixes.len() + 1];
+    for (i, prefix) in prefixes.iter().enumerate().rev() {
+        suffix_lower_bounds[i] = suffix_lower_bounds[i + 1].min(prefix.local_lower_bound);
+    }
+
+    let worker_stats = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|worker| {
+                let (problem, shared, best_time, prefixes) =
+                    (&problem, &shared, &best_time, &prefixes);
+                let (emit_solution, worst_time_of_interest) =
+                    (&emit_solution, &worst_time_of_interest);
+                let suffix_lower_bounds = &suffix_lower_bounds;
+
+                scope.spawn(move || {
+                    let emit = |solution: &[NodeIdx], time: Time| -> Time {
+                        let mut emit_solution = emit_solution.lock().unwrap();
+                        best_time.fetch_min(time, Ordering::Relaxed);
+                        let worst = (emit_solution)(solution, time).min(time_bound);
+                        worst_time_of_interest.store(worst, Ordering::Relaxed);
+                        worst
+                    };
+                    let mut cx = SolverContext::new(problem, settings, emit);
+                    cx.time_bound = time_bound;
+                    cx.shared_worst_time_of_interest = Some(worst_time_of_interest);
+                    if let Some(progress) = &settings.progress {
+                        cx.report_progress = Some(Box::new(move |worker_progress: &Progress| {
+                            let mut shared = shared.lock().unwrap();
+                            shared.workers[worker] = *worker_progress;
+
+                            let best_time = best_time.load(Ordering::Relaxed);
+                            let best_time = (best_time != Time::MAX).then_some(best_time);
+                            let mut total = Progress {
+                                iterations: stats.iterations,
+                                cut_branches: stats.cut_branches,
+                                best_time,
+                                lower_bound: suffix_lower_bounds[shared.next_prefix],
+                                explored: shared.finished_prefixes as f64,
+                            };
+                            for worker in &shared.workers {
+                                total.iterations += worker.iterations;
+                                total.cut_branches += worker.cut_branches;
+                                total.lower_bound = total.lower_bound.min(worker.lower_bound);
+                                total.explored += worker.explored;
+                            }
+                            total.lower_bound =
+                                total.lower_bound.min(best_time.unwrap_or(Time::MAX));
+                            total.explored /= prefixes.len() as f64;
+
+                            progress.report(&total);
+                        }));
+                    }
+
+                    while !cx.stopped {
+                        let prefix = {
+                            let mut shared = shared.lock().unwrap();
+                            let Some(prefix) = prefixes.get(shared.next_prefix) else {
+                                break;
+                            };
+                            shared.next_prefix += 1;
+                            shared.workers[worker].lower_bound = prefix.local_lower_bound;
+                            shared.workers[worker].explored = 0.0;
+                            prefix
+                        };
+                        cx.resume(prefix);
+
+                        if !cx.stopped {
+                            let mut shared = shared.lock().unwrap();
+                            shared.finished_prefixes += 1;
+                            shared.workers[worker].lower_bound = Time::MAX;
+                            shared.workers[worker].explored = 0.0;
+                        }
+                    }
+
+                    cx.stats()
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    for worker_stats in worker_stats {
+        stats += worker_stats;
+    }
+
+    // prefixes which no worker got to before stopping
+    let next_prefix = shared.into_inner().unwrap().next_prefix;
+    for prefix in prefixes.get(next_prefix..).unwrap_or_default() {
+        stats.complete = false;
+        stats.lower_bound = stats.lower_bound.min(prefix.local_lower_bound);
+    }
+    let best_time = best_time.into_inner();
+    report_finished(
+        settings,
+        &stats,
+        (best_time != Time::MAX).then_some(best_time),
+    );
+
+    Ok(stats)
 }
 
-pub struct PossibleConnection<'a> {
+pub struct PossibleConnection {
     pub start: NodeIdx,
     pub end: NodeIdx,
 
-    pub path: &'a [NodeIdx],
-    pub time: Time,
+    /// fastest route with the new connection, where it takes no time
+    pub route: Route,
+}
+
+/// Two new connections which make a route faster together than each of them on its own
+pub struct PossibleConnectionPair {
+    pub first: Connection,
+    pub second: Connection,
+
+    /// fastest route with both new connections, where they take no time
+    pub route: Route,
+    /// fastest time with only one of the connections, `None` if neither beats `time_to_beat` on its own
+    pub single_time: Option<Time>,
 }
 
-/// `emit_new_connection` gets called for each possible connection
-pub fn find_new_connections<F>(
+/// Tries every missing connection at a time of `0`, and calls `emit_new_connection` for each one which makes a route
+/// at least as fast as `time_to_beat` possible. The connections are tested on `threads` worker threads, and
+/// `report_progress` gets called with the amount of tested and total connections after each one.
+///
+/// Connections whose lower bound is already slower than `time_to_beat` are skipped without a search,
+/// and the others only search for routes faster than it.
+pub fn find_new_connections<F, P>(
     table: &[Vec<u32>],
     settings: &SolverSettings,
     time_to_beat: Time,
+    threads: usize,
     mut emit_new_connection: F,
-) where
-    F: FnMut(PossibleConnection<'_>),
+    mut report_progress: P,
+) -> Result<(), SettingsError>
+where
+    F: FnMut(PossibleConnection),
+    P: FnMut(usize, usize),
 {
-    let n = table[0].len();
-
-    let files: Vec<FileInfo> = collect_files(table);
-    let nodes: Vec<PlaceInfo> = collect_nodes(n, &files);
+    let search = NewConnectionSearch::new(table, settings)?;
+    let connections = search.missing_connections();
+
+    let total = connections.len();
+    search.test_in_parallel(
+        &connections,
+        threads,
+        |&connection| search.best_with(&[connection], time_to_beat),
+        |&connection, solution, tested| {
+            if let Some((path, time)) = solution {
+                emit_new_connection(PossibleConnection {
+                    start: connection.from,
+                    end: connection.to,
+                    route: route_with_new_connections(table, settings, &[connection], &path, time),
+                });
+            }
+            report_progress(tested, total);
+        },
+    );
+    Ok(())
+}
 
-    let mut connections = Vec::with_capacity((nodes.len() - 1) * (nodes.len() - 1));
-    for start in 0..nodes.len() - 1 {
-        for end in 1..nodes.len() - 1 {
-            connections.push((start, end));
+/// Like [`find_new_connections`], but tries pairs of missing connections, and calls `emit_new_connection_pair`
+/// for each pair which makes a route possible that is faster than with either connection on its own.
+///
+/// Every single connection is tested first, so their times bound the search for each pair.
+/// `report_progress` counts both the single connections and the pairs.
+pub fn find_new_connection_pairs<F, P>(
+    table: &[Vec<u32>],
+    settings: &SolverSettings,
+    time_to_beat: Time,
+    threads: usize,
+    mut emit_new_connection_pair: F,
+    mut report_progress: P,
+) -> Result<(), SettingsError>
+where
+    F: FnMut(PossibleConnectionPair),
+    P: FnMut(usize, usize),
+{
+    let search = NewConnectionSearch::new(table, settings)?;
+    let connections = search.missing_connections();
+
+    // a route can't use two connections with the same start or end, or go back and forth between two maps
+    let mut pairs = Vec::new();
+    for (i, first) in connections.iter().enumerate() {
+        for second in &connections[i + 1..] {
+            if first.from != second.from
+                && first.to != second.to
+                && (first.from != second.to || first.to != second.from)
+            {
+                pairs.push((*first, *second));
+            }
         }
     }
 
-    for (connection_start, connection_end) in connections {
-        if connection_end == connection_start
-            || connection_start == 0 && connection_end == nodes.len()
-        {
-            continue;
-        }
+    let total = connections.len() + pairs.len();
+    let mut single_times = vec![None; connections.len()];
+    let indices: Vec<usize> = (0..connections.len()).collect();
+    search.test_in_parallel(
+        &indices,
+        threads,
+        |&i| search.best_with(&[connections[i]], time_to_beat),
+        |&i, solution, tested| {
+            single_times[i] = solution.map(|(_, time)| time);
+            report_progress(tested, total);
+        },
+    );
+    if settings.should_stop() {
+        return Ok(());
+    }
 
-        if files
-            .iter()
-            .any(|file| file.start == connection_start && file.end == connection_end)
-        {
-            continue;
+    let single_time = |connection: &Connection| {
+        let i =
+            connections.binary_search_by_key(&(connection.from, connection.to), |c| (c.from, c.to));
+        single_times[i.unwrap()]
+    };
+    search.test_in_parallel(
+        &pairs,
+        threads,
+        |(first, second)| {
+            let single_time = [single_time(first), single_time(second)]
+                .into_iter()
+                .flatten()
+                .min();
+            let time_to_beat = single_time.map_or(time_to_beat, |time| {
+                time_to_beat.min(time.saturating_sub(1))
+            });
+            search
+                .best_with(&[*first, *second], time_to_beat)
+                .filter(|&(_, time)| single_time.is_none_or(|single_time| time < single_time))
+                .map(|solution| (solution, single_time))
+        },
+        |&(first, second), solution, tested| {
+            if let Some(((path, time), single_time)) = solution {
+                emit_new_connection_pair(PossibleConnectionPair {
+                    first,
+                    second,
+                    route: route_with_new_connections(
+                        table,
+                        settings,
+                        &[first, second],
+                        &path,
+                        time,
+                    ),
+                    single_time,
+                });
+            }
+            report_progress(connections.len() + tested, total);
+        },
+    );
+    Ok(())
+}
+
+fn route_with_new_connections(
+    table: &[Vec<u32>],
+    settings: &SolverSettings,
+    connections: &[Connection],
+    maps: &[NodeIdx],
+    time: Time,
+) -> Route {
+    let mut table = table.to_vec();
+    for connection in connections {
+        table[connection.from][connection.to] = 0;
+    }
+    Route::new(&table, settings, maps, time)
+}
+
+/// Finds the fastest routes after adding new connections to the table.
+struct NewConnectionSearch {
+    n: usize,
+    files: Vec<FileInfo>,
+    settings: SolverSettings,
+}
+
+impl NewConnectionSearch {
+    fn new(
+        table: &[Vec<u32>],
+        settings: &SolverSettings,
+    ) -> Result<NewConnectionSearch, SettingsError> {
+        settings.check_table(table)?;
+        // known routes are for the unchanged table, and only the best solution of each connection is kept
+        let settings = SolverSettings {
+            warm_start: WarmStart {
+                routes: Vec::new(),
+                time: None,
+                solutions: 1,
+                ..settings.warm_start
+            },
+            progress: None,
+            ..settings.clone()
+        };
+        Ok(NewConnectionSearch {
+            n: table.len(),
+            files: collect_files(table),
+            settings,
+        })
+    }
+
+    /// connections missing from the table, sorted by start and end
+    fn missing_connections(&self) -> Vec<Connection> {
+        let settings = &self.settings;
+
+        // connections to the start are restarts, and nothing comes after the finish if it's the only one
+        let finish = settings.finish.single(self.n);
+        let mut connections = Vec::with_capacity((self.n - 1) * (self.n - 1));
+        for start in (0..self.n).filter(|&node| Some(node) != finish) {
+            for end in (0..self.n).filter(|&node| node != settings.start && Some(node) != finish) {
+                let connection = Connection {
+                    from: start,
+                    to: end,
+                };
+                if start != end
+                    && !settings.forbidden_connections.contains(&connection)
+                    && !self
+                        .files
+                        .iter()
+                        .any(|file| file.start == start && file.end == end)
+                {
+                    connections.push(connection);
+                }
+            }
         }
+        connections
+    }
 
-        let files = edit_files_to_test_new_connection(&files, connection_start, connection_end);
+    /// Fastest route using all of `connections` at a time of `0`, if it is at least as fast as `time_to_beat`.
+    /// Connections whose lower bound is already slower are skipped without a search, and the others only
+    /// search for routes faster than it.
+    fn best_with(
+        &self,
+        connections: &[Connection],
+        time_to_beat: Time,
+    ) -> Option<(Vec<NodeIdx>, Time)> {
+        let files =
+            edit_files_to_test_new_connections(&self.files, self.settings.start, connections);
+        let settings = SolverSettings {
+            warm_start: WarmStart {
+                time: Some(time_to_beat),
+                ..self.settings.warm_start.clone()
+            },
+            ..self.settings.clone()
+        };
+        // removing the connections of the tested ones may leave maps without any, so the table size is kept
+        let problem = Problem::new(&files, self.n, &settings)
+            .expect("the settings are checked when creating the search");
+        if problem.root_lower_bound(&settings) > time_to_beat {
+            return None;
+        }
 
         let mut solution = None;
-        solve_files(&files, settings, |s: &[NodeIdx], time: Time| -> Time {
-            let solution = solution.get_or_insert_with(|| (s.to_vec(), time));
-            if time < solution.1 {
-                *solution = (s.to_vec(), time);
-            }
-            solution.1.min(time_to_beat)
-        });
-        let Some(new_solution) = solution else { continue };
+        solve_problem(&problem, &settings, emit_only_best(&mut solution));
+        solution
+    }
 
-        if new_solution.1 > time_to_beat {
-            continue;
+    /// Runs `test` for each item on `threads` worker threads, and calls `emit` with the results on this thread
+    /// together with the amount of items tested so far.
+    fn test_in_parallel<T, R>(
+        &self,
+        items: &[T],
+        threads: usize,
+        test: impl Fn(&T) -> R + Sync,
+        mut emit: impl FnMut(&T, R, usize),
+    ) where
+        T: Sync,
+        R: Send,
+    {
+        if threads <= 1 {
+            for (i, item) in items.iter().enumerate() {
+                if self.settings.should_stop() {
+                    return;
+                }
+                emit(item, test(item), i + 1);
+            }
+            return;
         }
 
-        emit_new_connection(PossibleConnection {
-            start: connection_start,
-            end: connection_end,
-            path: &new_solution.0,
-            time: new_solution.1,
+        // the workers take the next item to test, and send the results back to be emitted on this thread
+        let next_item = std::sync::atomic::AtomicUsize::new(0);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::scope(|scope| {
+            for _ in 0..threads.min(items.len()) {
+                let (next_item, test) = (&next_item, &test);
+                let sender = sender.clone();
+                scope.spawn(move || loop {
+                    let i = next_item.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else {
+                        break;
+                    };
+                    if self.settings.should_stop() {
+                        break;
+                    }
+                    if sender.send((i, test(item))).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(sender);
+
+            for (tested, (i, result)) in receiver.into_iter().enumerate() {
+                emit(&items[i], result, tested + 1);
+            }
         });
     }
 }
 
-/// - remove start-* and *-end
+/// - remove start-* and *-end of each connection
 /// - add start-end with time 0
-fn edit_files_to_test_new_connection(
+fn edit_files_to_test_new_connections(
     files: &[FileInfo],
-    start: NodeIdx,
-    end: NodeIdx,
+    route_start: NodeIdx,
+    connections: &[Connection],
 ) -> Vec<FileInfo> {
     let mut new_files = files.to_vec();
-    new_files.retain(|file| file.start != start && file.end != end);
+    // restarting stays possible from everywhere
+    new_files.retain(|file| {
+        file.end == route_start
+            || connections
+                .iter()
+                .all(|connection| file.start != connection.from && file.end != connection.to)
+    });
+
+    for connection in connections {
+        new_files.insert(
+            if connection.from == route_start {
+                0
+            } else {
+                new_files.len()
+            },
+            FileInfo {
+                start: connection.from,
+                end: connection.to,
+                time: 0,
+            },
+        );
+    }
+
+    new_files
+}
+
+/// everything derived from the files which stays the same during the search
+struct Problem {
+    n: usize,
+    nodes: Vec<PlaceInfo>,
+    start: NodeIdx,
+    finish: NodeIdx,
+
+    /// `matrix[from * n + to]`, `Time::MAX` for missing connections
+    matrix: Vec<Time>,
+    /// time to restart from each node, `Time::MAX` if that's not possible
+    restart_times: Vec<Time>,
+    /// nodes which have to be visited before each node
+    predecessors: Vec<Vec<NodeIdx>>,
+    /// time added for leaving out each node, `None` if it has to be visited
+    skip_penalties: Vec<Option<Time>>,
+    min_optional_visits: usize,
+    /// cheapest incoming connection of each node, or its skip penalty if that's lower
+    lowest_times: Vec<Time>,
+    global_lower_bound: Time,
+    /// the search uses its own node numbers, which are translated back before emitting routes
+    numbering: numbering::Numbering,
+}
+
+impl Problem {
+    /// problem for the `files` of a table with `n` nodes
+    fn new(
+        files: &[FileInfo],
+        n: usize,
+        settings: &SolverSettings,
+    ) -> Result<Problem, SettingsError> {
+        settings.check(n)?;
+        if let Some(file) = files.iter().find(|file| file.start >= n || file.end >= n) {
+            return Err(SettingsError(format!(
+                "connection {}-{} is outside of the table (0..{n})",
+                file.start, file.end
+            )));
+        }
+
+        let numbering = numbering::Numbering::new(n, settings);
+        let files = numbering.files(files);
+        let n = numbering.len();
+        let start = 0;
+        let finish = n - 1;
+
+        // connections back to the start node are restarts
+        let (restarts, files): (Vec<_>, Vec<_>) =
+            files.iter().cloned().partition(|file| file.end == start);
+
+        let nodes: Vec<PlaceInfo> = collect_nodes(n, &files);
+
+        let mut matrix = vec![Time::MAX; n * n];
+        for file in &files {
+            matrix[file.start * n + file.end] = file.time;
+        }
+
+        let mut restart_times = match settings.restart_penalty {
+            Some(restart_penalty) => vec![restart_penalty; n],
+            None => {
+                let mut restart_times = vec![Time::MAX; n];
+                for file in &restarts {
+                    restart_times[file.start] = file.time;
+                }
+                restart_times
+            }
+        };
+
+        // forbidden restarts are left out of the files already, but not out of the restart penalty
+        for connection in &settings.forbidden_connections {
+            if connection.to == settings.start {
+                if let Some(node) = numbering.exit(connection.from) {
+                    restart_times[node] = Time::MAX;
+                }
+            }
+        }
+
+        let mut predecessors = vec![Vec::new(); n];
+        for precedence in &settings.precedences {
+            let (before, after) = (
+                numbering.node(precedence.before),
+                numbering.node(precedence.after),
+            );
+            if before == after {
+                let chain = numbering.chain(before);
+                let position = |node| chain.iter().position(|&other| other == node);
+                if position(precedence.before) < position(precedence.after) {
+                    continue;
+                }
+                // the chain visits them the wrong way around, so it can't be visited at all
+            }
+            predecessors[after].push(before);
+        }
+
+        let mut skip_penalties = vec![None; n];
+        for optional in &settings.optional_maps.maps {
+            skip_penalties[numbering.node(optional.map)] = Some(optional.skip_penalty);
+        }
+
+        let mut lowest_times = collect_lowest_times(n, &nodes);
+        // an optional node is either entered or skipped
+        for (lowest_time, skip_penalty) in lowest_times.iter_mut().zip(&skip_penalties) {
+            if let Some(skip_penalty) = *skip_penalty {
+                *lowest_time = (*lowest_time).min(skip_penalty);
+            }
+        }
+        // saturates for nodes without incoming connections, which cuts the search right away.
+        // The recursive search wrapped around here instead, and searched those tables without cutting a branch.
+        let global_lower_bound: Time = lowest_times.iter().fold(0, |a, &b| a.saturating_add(b));
+
+        Ok(Problem {
+            n,
+            nodes,
+            start,
+            finish,
+            matrix,
+            restart_times,
+            predecessors,
+            skip_penalties,
+            min_optional_visits: settings.optional_maps.min_visited,
+            lowest_times,
+            global_lower_bound,
+            numbering,
+        })
+    }
+
+    /// whether every node which has to be visited before `node` is visited already
+    fn can_visit(&self, node: NodeIdx, is_visited: impl Fn(NodeIdx) -> bool) -> bool {
+        self.predecessors[node]
+            .iter()
+            .all(|&before| before == self.start || is_visited(before))
+    }
+
+    /// lower bound of every route, using the assignment relaxation which is cheap compared to a search
+    fn root_lower_bound(&self, settings: &SolverSettings) -> Time {
+        let state = bounds::BoundState {
+            pos: self.start,
+            can_go: &vec![true; self.n],
+            restarts_allowed: settings.max_restarts != Some(0),
+        };
+        self.global_lower_bound
+            .max(bounds::assignment(self, &state))
+    }
 
-    new_files.insert(
-        if start == 0 { 0 } else { new_files.len() },
-        FileInfo {
-            start,
-            end,
-            time: 0,
-        },
-    );
+    /// amount of nodes every route has to enter, including the finish
+    fn shortest_route_len(&self) -> usize {
+        let required = (1..self.finish)
+            .filter(|&node| !self.is_optional(node))
+            .count();
+        required + self.min_optional_visits + 1
+    }
 
-    new_files
+    fn is_optional(&self, node: NodeIdx) -> bool {
+        self.skip_penalties[node].is_some()
+    }
+
+    /// Total penalty for the nodes left out of a route which visited the nodes `is_visited` returns true for,
+    /// or `None` if that route is missing required nodes.
+    fn skip_penalty(&self, is_visited: impl Fn(NodeIdx) -> bool) -> Option<Time> {
+        let mut penalty: Time = 0;
+        let mut optional_visits = 0;
+        for node in (0..self.n).filter(|&node| node != self.start && node != self.finish) {
+            match (is_visited(node), self.skip_penalties[node]) {
+                (true, Some(_)) => optional_visits += 1,
+                (true, None) => {}
+                (false, Some(skip_penalty)) => penalty = penalty.saturating_add(skip_penalty),
+                (false, None) => return None,
+            }
+        }
+        (optional_visits >= self.min_optional_visits).then_some(penalty)
+    }
+
+    fn restart_time(&self, from: NodeIdx) -> Option<Time> {
+        match self.restart_times[from] {
+            Time::MAX => None,
+            time => Some(time),
+        }
+    }
 }
 
 fn collect_lowest_times(n: usize, nodes: &[PlaceInfo]) -> Vec<u32> {
@@ -205,18 +1414,25 @@ fn collect_lowest_times(n: usize, nodes: &[PlaceInfo]) -> Vec<u32> {
     lowest_times
 }
 
-/// extract file connections from table
-fn collect_files(table: &[Vec<u32>]) -> Vec<FileInfo> {
+/// Lower bound of every route through `table`, from the cheapest assignment of a successor to each map.
+pub(crate) fn root_lower_bound(
+    table: &[Vec<u32>],
+    settings: &SolverSettings,
+) -> Result<Time, SettingsError> {
+    let problem = Problem::new(&collect_files(table), table.len(), settings)?;
+    Ok(problem.root_lower_bound(settings))
+}
+
+/// the connections of a table which exist, ordered by start and end
+pub(crate) fn collect_files(table: &[Vec<u32>]) -> Vec<FileInfo> {
     table
         .iter()
         .enumerate()
         .flat_map(|(start, row)| {
-            let mut row = row.iter().enumerate();
-            let restart = row.next().unwrap().0;
-            // TODO
-            assert!(restart == 190 || restart == 0);
-
-            row.map(move |(end, &time)| FileInfo { start, end, time })
+            // the first column is the restart time, i.e. a connection to the start node
+            row.iter()
+                .enumerate()
+                .map(move |(end, &time)| FileInfo { start, end, time })
         })
         .filter(|file| file.time < 60000 && file.start != file.end)
         .collect()
@@ -252,41 +1468,192 @@ fn collect_nodes(n: usize, files: &[FileInfo]) -> Vec<PlaceInfo> {
     nodes
 }
 
+type ReportProgress<'a> = Box<dyn FnMut(&Progress) + 'a>;
+
 struct SolverContext<'a, F> {
     settings: &'a SolverSettings,
+    problem: &'a Problem,
     nodes: &'a [PlaceInfo],
 
+    /// time of the route up to the previous node
+    time: Time,
     local_lower_bound: Time,
     cut_branches: u32,
-    lowest_times: Vec<Time>,
+    cut_branches_by_bound: [u32; LowerBound::ALL.len()],
+    lowest_times: &'a [Time],
     worst_time_of_interest: Time,
+    shared_worst_time_of_interest: Option<&'a AtomicU32>,
+    /// the worst time of interest never goes above this, see [`WarmStart::time`]
+    time_bound: Time,
 
     solutions_found: u32,
+    best_time: Option<Time>,
     emit_solution: F,
 
+    report_progress: Option<ReportProgress<'a>>,
+    next_progress_report: u32,
+
     n: usize,
     start: NodeIdx,
     finish: NodeIdx,
 
     iterations: u32,
+    /// iterations between checks whether the search should stop
+    stop_check_interval: u32,
+    next_stop_check: u32,
     restart_count: u32,
 
-    index: usize,
     visit_count: usize,
     can_go: Vec<bool>,
 
+    /// the current route, ending in the node which is currently being visited
+    trail: Vec<NodeIdx>,
+
+    split_depth: Option<usize>,
+    prefixes: Vec<Prefix>,
+
+    stopped: bool,
+    /// lower bound of everything left unexplored after stopping
+    unexplored_lower_bound: Time,
+}
+
+/// a node of the current route on the explicit search stack, replacing recursion
+struct Frame {
+    pos: NodeIdx,
+    /// time needed to get to `pos` from the previous node
+    added_time: Time,
+    update_lower_bound: Time,
+    /// lower bound for every route continuing from here
+    lower_bound: Time,
+    /// local lower bound after entering one of the targets, without the time to get there
+    child_lower_bound: Time,
+
+    step: Step,
+    /// amount of branches to take from here, including the restart
+    branches: usize,
+    must_restart: bool,
+}
+
+/// what to try next from a frame
+#[derive(Clone, Copy)]
+enum Step {
+    /// the only way left to reach this node is right now
+    DeadEnd(NodeIdx),
+    /// index into the targets of the frame's node
+    Target(usize),
+    Restart,
+    Done,
+}
+
+#[derive(Clone, Copy)]
+enum Move {
+    Visit(NodeIdx),
+    Restart,
+}
+
+/// progress of the parallel search, shared between the workers
+struct SharedProgress {
+    next_prefix: usize,
+    finished_prefixes: usize,
+    /// latest progress of each worker, within the prefix it is working on
+    workers: Vec<Progress>,
+}
+
+/// snapshot of the search state at the end of a route prefix, from which the search can be resumed
+struct Prefix {
     trail: Vec<NodeIdx>,
+    can_go: Vec<bool>,
+    time: Time,
+    local_lower_bound: Time,
+    visit_count: usize,
+    restart_count: u32,
 }
 
-impl<F> SolverContext<'_, F>
+impl<'a, F> SolverContext<'a, F>
 where
     F: FnMut(&[NodeIdx], Time) -> Time,
 {
+    fn new(problem: &'a Problem, settings: &'a SolverSettings, emit_solution: F) -> Self {
+        let n = problem.n;
+        SolverContext {
+            settings,
+            problem,
+            n,
+            time: 0,
+            local_lower_bound: problem.global_lower_bound,
+            cut_branches: 0,
+            cut_branches_by_bound: [0; LowerBound::ALL.len()],
+            lowest_times: &problem.lowest_times,
+            worst_time_of_interest: u32::MAX,
+            shared_worst_time_of_interest: None,
+            time_bound: Time::MAX,
+            solutions_found: 0,
+            best_time: None,
+            emit_solution,
+            report_progress: None,
+            next_progress_report: settings
+                .progress
+                .as_ref()
+                .map_or(u32::MAX, |progress| progress.interval),
+            start: problem.start,
+            finish: problem.finish,
+            iterations: 0,
+            stop_check_interval: (STOP_CHECK_WORK / settings.lower_bound.iteration_work(n)).max(1),
+            next_stop_check: 0,
+            restart_count: 0,
+            can_go: vec![true; n],
+            trail: Vec::with_capacity(n + problem.nodes[problem.start].targets.len()),
+            visit_count: 0,
+            nodes: &problem.nodes,
+            split_depth: None,
+            prefixes: Vec::new(),
+            stopped: false,
+            unexplored_lower_bound: Time::MAX,
+        }
+    }
+
+    fn stats(&self) -> Stats {
+        Stats {
+            solutions_found: self.solutions_found,
+            iterations: self.iterations,
+            cut_branches: self.cut_branches,
+            cut_branches_by_bound: self.cut_branches_by_bound,
+            complete: !self.stopped,
+            lower_bound: self.worst_time_of_interest.min(self.unexplored_lower_bound),
+            warm_start_bound: None,
+        }
+    }
+
+    /// continue the search below `prefix`, as if `path_find` had reached it
+    fn resume(&mut self, prefix: &Prefix) {
+        let (&pos, trail) = prefix.trail.split_last().unwrap();
+        self.trail.clear();
+        self.trail.extend_from_slice(trail);
+        self.can_go.copy_from_slice(&prefix.can_go);
+        self.time = prefix.time;
+        self.local_lower_bound = prefix.local_lower_bound;
+        self.visit_count = prefix.visit_count;
+        self.restart_count = prefix.restart_count;
+
+        // the search may have been stopped while the prefix waited
+        self.next_stop_check = self.iterations;
+        self.path_find(pos);
+    }
+
+    fn precedences_met(&self, target: NodeIdx) -> bool {
+        self.problem
+            .can_visit(target, |before| !self.can_go[before])
+    }
+
     fn can_restart(&self, pos: NodeIdx, must: bool) -> bool {
         if self.settings.only_required_restarts && !must {
             return false;
         }
 
+        if self.problem.restart_time(pos).is_none() {
+            return false;
+        }
+
         match self.settings.max_restarts {
             None => pos != self.start,
             Some(max_restarts) => pos != self.start && (self.restart_count < max_restarts),
@@ -297,10 +1664,16 @@ where
         self.n - 1
     }
 
-    fn emit_solution(&mut self) {
-        self.solutions_found += 1;
+    /// penalty for the nodes left out of the current trail, `None` if it's missing required nodes
+    fn skip_penalty(&self) -> Option<Time> {
+        if self.visit_count == self.place_count() {
+            return Some(0);
+        }
+        self.problem.skip_penalty(|node| !self.can_go[node])
+    }
 
-        let solution = &self.trail[0..self.index + 1];
+    fn emit_solution(&mut self, skip_penalty: Time) {
+        let solution = &self.trail[..];
         let time: Time = solution
             .windows(2)
             .map(|segment| {
@@ -308,48 +1681,276 @@ where
                 let from = segment[0];
 
                 if to == self.start {
-                    190
+                    self.problem.restart_times[from]
                 } else {
                     self.nodes[from].frames_to(to)
                 }
             })
-            .sum();
+            .sum::<Time>()
+            + skip_penalty;
+        // the bound only prunes the search, so slower routes can still reach the finish
+        if time >= self.time_bound {
+            return;
+        }
+
+        self.solutions_found += 1;
+        self.best_time = Some(self.best_time.map_or(time, |best| best.min(time)));
+        let solution = self.problem.numbering.to_original(solution);
+        self.worst_time_of_interest = (self.emit_solution)(&solution, time).min(self.time_bound);
+        // the callback may have cancelled the search
+        self.next_stop_check = self.iterations;
+    }
+
+    /// lower bound of every route which hasn't been explored yet below the frames of `stack`
+    fn open_lower_bound(&self, stack: &[Frame]) -> Time {
+        let branch_lower_bound = |frame: &Frame, added_time: Time, lowest_time: Time| {
+            let child = frame.child_lower_bound.saturating_add(added_time) - lowest_time;
+            child.max(frame.lower_bound)
+        };
+
+        stack
+            .iter()
+            .filter_map(|frame| {
+                let place = &self.nodes[frame.pos];
+                let targets = match frame.step {
+                    Step::DeadEnd(target) => {
+                        let i = place.targets.iter().position(|&t| t == target).unwrap();
+                        i..i + 1
+                    }
+                    Step::Target(i) => i..place.targets.len(),
+                    Step::Restart | Step::Done => 0..0,
+                };
+                let restart = matches!(frame.step, Step::Target(_) | Step::Restart)
+                    .then(|| self.problem.restart_time(frame.pos))
+                    .flatten()
+                    .map(|restart_time| {
+                        branch_lower_bound(frame, restart_time, self.lowest_times[self.start])
+                    });
+
+                place.targets[targets.clone()]
+                    .iter()
+                    .zip(&place.times[targets])
+                    .map(|(&target, &time)| {
+                        branch_lower_bound(frame, time, self.lowest_times[target])
+                    })
+                    .chain(restart)
+                    .min()
+            })
+            .min()
+            .unwrap_or(Time::MAX)
+    }
+
+    /// estimate of the explored fraction of the subtree below the first frame of `stack`
+    fn explored_fraction(&self, stack: &[Frame]) -> f64 {
+        let mut explored = 0.0;
+        let mut weight = 1.0;
+
+        for (depth, frame) in stack.iter().enumerate() {
+            let started = match frame.step {
+                Step::DeadEnd(_) => 0,
+                Step::Target(i) => i,
+                Step::Restart => frame.branches - 1,
+                Step::Done => frame.branches,
+            };
+            // every frame but the last one has a branch in progress
+            let is_last = depth + 1 == stack.len();
+            let done = if is_last { started } else { started - 1 };
+
+            weight /= frame.branches as f64;
+            explored += weight * done as f64;
+        }
+
+        explored.min(1.0)
+    }
+
+    fn report_progress(&mut self, stack: &[Frame]) {
+        let interval = self.settings.progress.as_ref().unwrap().interval;
+        self.next_progress_report = self.iterations.saturating_add(interval);
+
+        let lower_bound = self.open_lower_bound(stack);
+        let progress = Progress {
+            iterations: self.iterations,
+            cut_branches: self.cut_branches,
+            best_time: self.best_time,
+            lower_bound: self
+                .best_time
+                .map_or(lower_bound, |best| best.min(lower_bound)),
+            explored: self.explored_fraction(stack),
+        };
+        if let Some(report_progress) = &mut self.report_progress {
+            report_progress(&progress);
+        }
+    }
+
+    /// lower bound for the time needed to visit every remaining node, coming from `pos`
+    fn remaining_lower_bound(&self, pos: NodeIdx) -> Time {
+        let state = bounds::BoundState {
+            pos,
+            can_go: &self.can_go,
+            restarts_allowed: self
+                .settings
+                .max_restarts
+                .is_none_or(|max_restarts| self.restart_count < max_restarts),
+        };
 
-        self.worst_time_of_interest = (self.emit_solution)(solution, time);
+        match self.settings.lower_bound {
+            LowerBound::CheapestIncoming => {
+                unreachable!("the cheapest incoming bound is tracked incrementally")
+            }
+            LowerBound::AvailableIncoming => bounds::available_incoming(self.problem, &state),
+            LowerBound::MaxInOut => bounds::available_incoming(self.problem, &state)
+                .max(bounds::available_outgoing(self.problem, &state)),
+            LowerBound::Assignment => bounds::assignment(self.problem, &state),
+        }
     }
 
+    /// search every route continuing the current trail at `pos`
     fn path_find(&mut self, pos: NodeIdx) {
-        self.trail[self.index] = pos;
+        let mut stack = Vec::new();
+        match self.enter(pos) {
+            Some(frame) => stack.push(frame),
+            None => {
+                self.trail.pop();
+            }
+        }
+
+        while !stack.is_empty() {
+            if self.iterations >= self.next_stop_check {
+                self.next_stop_check = self.iterations.saturating_add(self.stop_check_interval);
+                self.stopped = self.settings.should_stop();
+            }
+            if self.stopped {
+                self.unexplored_lower_bound = self
+                    .unexplored_lower_bound
+                    .min(self.open_lower_bound(&stack));
+                return;
+            }
+            if self.iterations >= self.next_progress_report {
+                self.report_progress(&stack);
+            }
+            let frame = stack.last_mut().unwrap();
+
+            match self.next_move(frame) {
+                Some(next) => {
+                    let (added_time, update_lower_bound) =
+                        (frame.added_time, frame.update_lower_bound);
+                    self.apply(next, added_time, update_lower_bound);
+
+                    let target = match next {
+                        Move::Visit(target) => target,
+                        Move::Restart => self.start,
+                    };
+                    match self.enter(target) {
+                        Some(frame) => stack.push(frame),
+                        None => {
+                            self.trail.pop();
+                            self.undo(next, added_time, update_lower_bound);
+                        }
+                    }
+                }
+                None => {
+                    let pos = frame.pos;
+                    stack.pop();
+                    self.trail.pop();
+
+                    // every frame except the first one was entered from its parent
+                    if let Some(parent) = stack.last() {
+                        let entered_by = if pos == self.start {
+                            Move::Restart
+                        } else {
+                            Move::Visit(pos)
+                        };
+                        self.undo(entered_by, parent.added_time, parent.update_lower_bound);
+                    }
+                }
+            }
+        }
+    }
+
+    fn apply(&mut self, next: Move, added_time: Time, update_lower_bound: Time) {
+        match next {
+            Move::Visit(target) => {
+                self.visit_count += 1;
+                self.can_go[target] = false;
+            }
+            Move::Restart => self.restart_count += 1,
+        }
+        self.local_lower_bound += update_lower_bound;
+        self.time += added_time;
+    }
+
+    fn undo(&mut self, next: Move, added_time: Time, update_lower_bound: Time) {
+        match next {
+            Move::Visit(target) => {
+                self.visit_count -= 1;
+                self.can_go[target] = true;
+            }
+            Move::Restart => self.restart_count -= 1,
+        }
+        self.local_lower_bound -= update_lower_bound;
+        self.time -= added_time;
+    }
+
+    /// Visits `pos` at the end of the trail.
+    /// Returns `None` if there's nothing left to explore from here, in which case the caller has to leave `pos` again.
+    fn enter(&mut self, pos: NodeIdx) -> Option<Frame> {
+        self.trail.push(pos);
+        let index = self.trail.len() - 1;
+
+        if self.split_depth == Some(index) {
+            self.prefixes.push(Prefix {
+                trail: self.trail.clone(),
+                can_go: self.can_go.clone(),
+                time: self.time,
+                local_lower_bound: self.local_lower_bound,
+                visit_count: self.visit_count,
+                restart_count: self.restart_count,
+            });
+            return None;
+        }
+
         self.iterations += 1;
 
         if pos == self.finish {
-            if self.visit_count == self.place_count() {
-                self.emit_solution();
+            if let Some(skip_penalty) = self.skip_penalty() {
+                self.emit_solution(skip_penalty);
             }
-            return;
+            return None;
         }
 
+        if let Some(shared) = self.shared_worst_time_of_interest {
+            self.worst_time_of_interest = shared.load(Ordering::Relaxed);
+        }
         if self.local_lower_bound >= self.worst_time_of_interest {
             self.cut_branches += 1;
-            return;
+            self.cut_branches_by_bound[LowerBound::CheapestIncoming as usize] += 1;
+            return None;
         }
 
-        let added_time = if self.index == 0 {
+        let added_time = if index == 0 {
             0
+        } else if pos == self.start {
+            self.problem.restart_times[self.trail[index - 1]]
         } else {
-            if self.trail[self.index] == self.start {
-                self.settings.restart_penalty
-            } else {
-                self.nodes[self.trail[self.index - 1]].frames_to(self.trail[self.index])
-            }
+            self.nodes[self.trail[index - 1]].frames_to(pos)
         };
         let update_lower_bound = added_time - self.lowest_times[pos];
 
-        let targets = &self.nodes[pos].targets;
+        let mut lower_bound = self.local_lower_bound;
+        if self.settings.lower_bound != LowerBound::CheapestIncoming {
+            lower_bound = lower_bound
+                .max((self.time + added_time).saturating_add(self.remaining_lower_bound(pos)));
+            if lower_bound >= self.worst_time_of_interest {
+                self.cut_branches += 1;
+                self.cut_branches_by_bound[self.settings.lower_bound as usize] += 1;
+                return None;
+            }
+        }
 
         let mut dead_end = None;
-        'targets: for &target in targets {
-            if self.can_go[target] {
+        'targets: for &target in &self.nodes[pos].targets {
+            // optional nodes can be left out instead
+            if self.can_go[target] && !self.problem.is_optional(target) {
                 let node = &self.nodes[target];
                 for &targeter in &node.targeters {
                     if self.can_go[targeter] {
@@ -357,59 +1958,63 @@ where
                     }
                 }
 
-                if dead_end.is_some() {
-                    return;
+                // the dead end has to be visited next, which the precedences might not allow
+                if dead_end.is_some() || !self.precedences_met(target) {
+                    return None;
                 }
 
                 dead_end = Some(target);
             }
         }
 
-        if let Some(dead_end) = dead_end {
-            self.visit_count += 1;
-            self.index += 1;
-            self.can_go[dead_end] = false;
-            self.local_lower_bound += update_lower_bound;
-
-            self.path_find(dead_end);
-
-            self.local_lower_bound -= update_lower_bound;
-            self.visit_count -= 1;
-            self.index -= 1;
-            self.can_go[dead_end] = true;
-            return;
-        }
-
-        let mut must_restart = true;
-        for &target in targets {
-            if self.can_go[target] {
-                self.visit_count += 1;
-                self.index += 1;
-                self.can_go[target] = false;
-                self.local_lower_bound += update_lower_bound;
-
-                self.path_find(target);
+        Some(Frame {
+            pos,
+            added_time,
+            update_lower_bound,
+            lower_bound,
+            child_lower_bound: self.local_lower_bound + update_lower_bound,
+            step: match dead_end {
+                Some(dead_end) => Step::DeadEnd(dead_end),
+                None => Step::Target(0),
+            },
+            branches: match dead_end {
+                Some(_) => 1,
+                None => self.nodes[pos].targets.len() + 1,
+            },
+            must_restart: true,
+        })
+    }
 
-                self.local_lower_bound -= update_lower_bound;
-                self.visit_count -= 1;
-                self.index -= 1;
-                self.can_go[target] = true;
+    fn next_move(&self, frame: &mut Frame) -> Option<Move> {
+        let targets = &self.nodes[frame.pos].targets;
 
-                must_restart = false;
+        loop {
+            match frame.step {
+                Step::DeadEnd(dead_end) => {
+                    frame.step = Step::Done;
+                    return Some(Move::Visit(dead_end));
+                }
+                Step::Target(i) => {
+                    let Some(&target) = targets.get(i) else {
+                        frame.step = Step::Restart;
+                        continue;
+                    };
+                    frame.step = Step::Target(i + 1);
+
+                    if self.can_go[target] && self.precedences_met(target) {
+                        frame.must_restart = false;
+                        return Some(Move::Visit(target));
+                    }
+                }
+                Step::Restart => {
+                    frame.step = Step::Done;
+                    if self.can_restart(frame.pos, frame.must_restart) {
+                        return Some(Move::Restart);
+                    }
+                }
+                Step::Done => return None,
             }
         }
-
-        if self.can_restart(pos, must_restart) {
-            self.index += 1;
-            self.restart_count += 1;
-            self.local_lower_bound += update_lower_bound;
-
-            self.path_find(self.start);
-
-            self.local_lower_bound -= update_lower_bound;
-            self.restart_count -= 1;
-            self.index -= 1;
-        }
     }
 }
 
@@ -425,32 +2030,715 @@ pub fn emit_only_best(
     }
 }
 
+/// Keeps the `max_solutions` fastest routes passed to [`emit_top_n_solutions`], without duplicates.
 pub fn emit_top_n_solutions(
     best_solutions: &mut Vec<(Vec<usize>, u32)>,
     max_solutions: usize,
 ) -> impl FnMut(&[usize], u32) -> u32 + '_ {
-    let mut previous_worst = u32::MAX;
-
+    let mut top = TopSolutions::new(max_solutions);
+    for (solution, time) in std::mem::take(best_solutions) {
+        top.insert(&solution, time);
+    }
     move |solution, time| {
-        // perfect compat with c# solver: accept every solution (even if obsolete) and return high worst interested time
-        let is_windup = best_solutions.len() < max_solutions;
+        if top.insert(solution, time).is_some() {
+            best_solutions.clone_from(&top.solutions);
+        }
+        top.worst_time()
+    }
+}
+
+/// Amount of connections used by one of the routes but not by the other, whichever way around is larger.
+/// Swapping two neighbouring maps changes three connections, moving a restart to another spot changes up to four.
+pub fn route_difference(a: &[NodeIdx], b: &[NodeIdx]) -> usize {
+    let connections = |route: &[NodeIdx]| {
+        let mut connections: Vec<_> = route.windows(2).map(|pair| (pair[0], pair[1])).collect();
+        connections.sort_unstable();
+        connections
+    };
+    let (a, b) = (connections(a), connections(b));
+    let only_in = |a: &[(NodeIdx, NodeIdx)], b: &[(NodeIdx, NodeIdx)]| {
+        a.iter()
+            .filter(|connection| b.binary_search(connection).is_err())
+            .count()
+    };
+    only_in(&a, &b).max(only_in(&b, &a))
+}
+
+/// The fastest unique routes found so far, sorted by time.
+///
+/// With a `min_difference`, only routes which differ from every faster kept route in at least that many
+/// connections (see [`route_difference`]) are kept. A new route replaces the slower kept routes it is too
+/// similar to, so the result matches picking routes greedily by time, except for routes which were only
+/// similar to a replaced one.
+#[derive(Debug, Clone, Default)]
+pub struct TopSolutions {
+    solutions: Vec<(Vec<NodeIdx>, Time)>,
+    max_solutions: usize,
+    min_difference: usize,
+}
 
-        if time < previous_worst || is_windup {
-            best_solutions.push((solution.to_vec(), time));
-            best_solutions.sort_by_key(|&(_, time)| time);
-            best_solutions.truncate(max_solutions);
+impl TopSolutions {
+    pub fn new(max_solutions: usize) -> TopSolutions {
+        TopSolutions::diverse(max_solutions, 0)
+    }
 
-            previous_worst = best_solutions
-                .iter()
-                .map(|&(_, time)| time)
-                .max()
-                .unwrap_or(std::u32::MAX);
+    pub fn diverse(max_solutions: usize, min_difference: usize) -> TopSolutions {
+        TopSolutions {
+            solutions: Vec::with_capacity(max_solutions + 1),
+            max_solutions,
+            min_difference,
         }
+    }
 
-        if is_windup {
-            u32::MAX
-        } else {
-            previous_worst
+    /// Adds a route if it is fast enough and not already kept.
+    /// Returns the index it was inserted at, routes after it may have been moved or dropped.
+    pub fn insert(&mut self, route: &[NodeIdx], time: Time) -> Option<usize> {
+        if time >= self.worst_time() {
+            return None;
+        }
+
+        let min_difference = self.min_difference;
+        let is_similar =
+            |kept: &[NodeIdx]| kept == route || route_difference(kept, route) < min_difference;
+        if self
+            .solutions
+            .iter()
+            .any(|(kept, kept_time)| *kept_time <= time && is_similar(kept))
+        {
+            return None;
+        }
+
+        self.solutions.retain(|(kept, _)| !is_similar(kept));
+        let index = self
+            .solutions
+            .partition_point(|&(_, kept_time)| kept_time <= time);
+        self.solutions.insert(index, (route.to_vec(), time));
+        self.solutions.truncate(self.max_solutions);
+        Some(index)
+    }
+
+    /// time a route needs to beat to be kept, `Time::MAX` until there are `max_solutions` routes
+    pub fn worst_time(&self) -> Time {
+        match self.solutions.last() {
+            Some(&(_, time)) if self.solutions.len() >= self.max_solutions => time,
+            _ => Time::MAX,
+        }
+    }
+
+    pub fn solutions(&self) -> &[(Vec<NodeIdx>, Time)] {
+        &self.solutions
+    }
+
+    pub fn into_solutions(self) -> Vec<(Vec<NodeIdx>, Time)> {
+        self.solutions
+    }
+
+    /// the kept routes with the time of each connection in `table`
+    pub fn into_routes(self, table: &[Vec<u32>], settings: &SolverSettings) -> Vec<Route> {
+        self.solutions
+            .iter()
+            .map(|(route, time)| Route::new(table, settings, route, *time))
+            .collect()
+    }
+
+    /// callback for the solvers which collects the routes into `self`
+    pub fn emit(&mut self) -> impl FnMut(&[NodeIdx], Time) -> Time + '_ {
+        move |route, time| {
+            self.insert(route, time);
+            self.worst_time()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{all_routes, random_table, settings_variants, Rng};
+    use crate::MISSING;
+    use std::time::Duration;
+
+    #[test]
+    fn reports_settings_which_dont_fit_the_table() {
+        let table = vec![vec![0, 100, 200], vec![100, 0, 300], vec![100, 100, 0]];
+        let settings = SolverSettings {
+            finish: Finish::Nodes(vec![3]),
+            ..Default::default()
+        };
+        let error = solve_table(&table, &settings, |_, _| Time::MAX).unwrap_err();
+        assert_eq!(error.to_string(), "finish 3 is outside of the table (0..3)");
+    }
+
+    #[test]
+    fn reports_tables_which_arent_square() {
+        let table = vec![
+            vec![0, 100, 200, 300],
+            vec![100, 0, 300, 400],
+            vec![100, 100, 0, 500],
+        ];
+        let error = solve_table(&table, &SolverSettings::default(), |_, _| Time::MAX).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "row 0 has 4 columns, but the table has 3 rows"
+        );
+        let error = solve_table_parallel(&table, &SolverSettings::default(), 2, |_, _| Time::MAX);
+        assert!(error.is_err());
+
+        let error = solve_files(
+            &collect_files(&table),
+            3,
+            &SolverSettings::default(),
+            |_, _| Time::MAX,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "connection 0-3 is outside of the table (0..3)"
+        );
+    }
+
+    #[test]
+    fn keeps_maps_without_connections() {
+        // the finish can't be reached, which doesn't make map 1 the finish instead
+        let table = vec![
+            vec![0, 100, MISSING],
+            vec![100, 0, MISSING],
+            vec![MISSING, MISSING, 0],
+        ];
+        let mut best = None;
+        let stats = solve_table(
+            &table,
+            &SolverSettings::default(),
+            emit_only_best(&mut best),
+        );
+        assert!(stats.unwrap().complete);
+        assert_eq!(best, None);
+    }
+
+    #[test]
+    fn counts_the_same_stats_as_the_recursive_search() {
+        const M: u32 = MISSING;
+        let mut table = vec![
+            vec![0, 310, 520, M, 450, 600, 380],
+            vec![150, 0, 230, 410, M, 350, 700],
+            vec![120, 640, 0, 200, 330, M, 510],
+            vec![180, M, 270, 0, 190, 420, 260],
+            vec![110, 390, M, 560, 0, 240, 300],
+            vec![160, 280, 450, M, 370, 0, 210],
+            vec![130, 520, 340, 290, M, 460, 0],
+        ];
+        let stats_with = |table: &[Vec<u32>], lower_bound| {
+            let settings = SolverSettings {
+                lower_bound,
+                ..Default::default()
+            };
+            let mut best = None;
+            let stats = solve_table(table, &settings, emit_only_best(&mut best)).unwrap();
+            let counts = (
+                stats.iterations,
+                stats.solutions_found,
+                stats.cut_branches,
+                stats.cut_branches_by_bound,
+            );
+            (counts, best.map(|(_, time)| time))
+        };
+
+        // counted by the recursive search before it was replaced
+        let expected = [
+            (39, 1, 21, [21, 0, 0, 0]),
+            (24, 1, 12, [9, 3, 0, 0]),
+            (24, 1, 12, [9, 0, 3, 0]),
+            (24, 1, 12, [9, 0, 0, 3]),
+        ];
+        for (lower_bound, expected) in LowerBound::ALL.into_iter().zip(expected) {
+            assert_eq!(
+                stats_with(&table, lower_bound),
+                (expected, Some(1380)),
+                "{lower_bound:?}"
+            );
+        }
+
+        // nothing leads to map 3, which the recursive search didn't notice
+        for row in &mut table {
+            row[3] = row[3].max(M);
+        }
+        table[3][3] = 0;
+        for lower_bound in LowerBound::ALL {
+            assert_eq!(
+                stats_with(&table, lower_bound),
+                ((1, 0, 1, [1, 0, 0, 0]), None),
+                "{lower_bound:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn searches_big_tables_with_many_restarts_on_a_small_stack() {
+        let mut rng = Rng::new(4);
+        let n = 320;
+        // the maps can only be reached from the start, so every one of them ends with a restart
+        let mut table = random_table(&mut rng, n, 100);
+        for time in &mut table[0][1..] {
+            *time = 100 + rng.below(900);
+        }
+
+        let start = Instant::now();
+        let settings = SolverSettings {
+            deadline: Some(start + Duration::from_millis(200)),
+            ..Default::default()
+        };
+        let (best, stats) = std::thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(move || {
+                let mut best = None;
+                let stats = solve_table(&table, &settings, emit_only_best(&mut best)).unwrap();
+                (best, stats)
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(2));
+        let (route, time) = best.unwrap();
+        let restarts = route[1..].iter().filter(|&&map| map == 0).count();
+        assert_eq!(restarts, n - 2);
+        assert!(stats.lower_bound <= time);
+    }
+
+    #[test]
+    fn parallel_search_finds_the_same_routes() {
+        let mut rng = Rng::new(11);
+        let mut with_restarts = 0;
+        for case in 0..40 {
+            // deep enough that the prefixes are split below the first level
+            let n = 9 + case % 2;
+            let table = random_table(&mut rng, n, 30);
+            // every kind of setting, limited to a few restarts to keep the search small
+            let variants = settings_variants(n);
+            let variant = variants[case % variants.len()].clone();
+            let precedences = if variant.precedences.is_empty() {
+                vec![
+                    Precedence {
+                        before: 3,
+                        after: 1,
+                    },
+                    Precedence {
+                        before: 2,
+                        after: 5,
+                    },
+                ]
+            } else {
+                variant.precedences.clone()
+            };
+            let settings = SolverSettings {
+                max_restarts: Some(variant.max_restarts.unwrap_or(2)),
+                precedences,
+                ..variant
+            };
+
+            let mut top = TopSolutions::new(10);
+            solve_table(&table, &settings, top.emit()).unwrap();
+            let bound = top.worst_time();
+
+            // every route below the bound, which doesn't depend on the order the workers find them in
+            let routes = |threads: usize| {
+                let mut routes = Vec::new();
+                let stats = solve_table_parallel(&table, &settings, threads, |route, time| {
+                    routes.push((route.to_vec(), time));
+                    bound
+                })
+                .unwrap();
+                assert!(stats.complete);
+                routes.sort_unstable_by(|(a, a_time), (b, b_time)| (a_time, a).cmp(&(b_time, b)));
+                routes
+            };
+            let serial = routes(1);
+            if serial.iter().any(|(route, _)| route[1..].contains(&0)) {
+                with_restarts += 1;
+            }
+            assert_eq!(routes(4), serial, "{settings:?} in {table:?}");
+        }
+        // routes with restarts are part of the comparison
+        assert!(with_restarts > 20);
+    }
+
+    #[test]
+    fn restarts_take_the_time_of_their_map() {
+        // maps 1 and 2 are dead ends, so the route has to restart from one of them
+        let table = vec![
+            vec![0, 10, 10, MISSING],
+            vec![50, 0, MISSING, 10],
+            vec![300, MISSING, 0, 10],
+            vec![0, 0, 0, 0],
+        ];
+        for backend in [Backend::BranchAndBound, Backend::HeldKarp] {
+            let settings = SolverSettings {
+                backend,
+                ..Default::default()
+            };
+            let mut solutions = Vec::new();
+            solve_table(&table, &settings, emit_top_n_solutions(&mut solutions, 2)).unwrap();
+            assert_eq!(
+                solutions,
+                [(vec![0, 1, 0, 2, 3], 80), (vec![0, 2, 0, 1, 3], 330)]
+            );
+            let route = Route::new(&table, &settings, &solutions[0].0, solutions[0].1);
+            assert_eq!(route.legs[1].time, 50);
+
+            // a restart penalty replaces the time of every map
+            let settings = SolverSettings {
+                restart_penalty: Some(100),
+                ..settings
+            };
+            let mut solutions = Vec::new();
+            solve_table(&table, &settings, emit_top_n_solutions(&mut solutions, 2)).unwrap();
+            let times: Vec<_> = solutions.iter().map(|&(_, time)| time).collect();
+            assert_eq!(times, [130, 130]);
+        }
+    }
+
+    #[test]
+    fn counts_the_connections_which_differ() {
+        let route = [0, 1, 2, 3, 4];
+        assert_eq!(route_difference(&route, &route), 0);
+        // swapping two neighbouring maps
+        assert_eq!(route_difference(&route, &[0, 2, 1, 3, 4]), 3);
+        // a restart instead of going from 2 to 3
+        assert_eq!(route_difference(&route, &[0, 1, 2, 0, 3, 4]), 2);
+    }
+
+    #[test]
+    fn keeps_only_diverse_routes() {
+        let mut top = TopSolutions::diverse(3, 4);
+        assert_eq!(top.insert(&[0, 1, 2, 3, 4, 5], 100), Some(0));
+        // too similar to the faster route
+        assert_eq!(top.insert(&[0, 2, 1, 3, 4, 5], 110), None);
+        assert_eq!(top.insert(&[0, 4, 3, 2, 1, 5], 120), Some(1));
+        // replaces the slower route it is similar to, but not the one it differs from enough
+        assert_eq!(top.insert(&[0, 2, 1, 3, 4, 5], 90), Some(0));
+        let times: Vec<_> = top.solutions().iter().map(|&(_, time)| time).collect();
+        assert_eq!(times, [90, 120]);
+
+        assert_eq!(top.insert(&[0, 3, 1, 4, 2, 5], 130), Some(2));
+        assert_eq!(top.worst_time(), 130);
+        assert_eq!(top.insert(&[0, 1, 3, 2, 4, 5], 140), None);
+    }
+
+    #[test]
+    fn diverse_solutions_start_with_the_fastest_route() {
+        let mut rng = Rng::new(13);
+        for case in 0..20 {
+            let n = 5 + case % 3;
+            let table = random_table(&mut rng, n, 20);
+            let settings = SolverSettings::default();
+
+            let mut best = None;
+            solve_table(&table, &settings, emit_only_best(&mut best)).unwrap();
+            let mut top = TopSolutions::diverse(5, 4);
+            solve_table(&table, &settings, top.emit()).unwrap();
+
+            let solutions = top.solutions();
+            assert_eq!(
+                solutions.first().map(|&(_, time)| time),
+                best.map(|(_, time)| time)
+            );
+            for (i, (route, _)) in solutions.iter().enumerate() {
+                for (other, _) in &solutions[..i] {
+                    assert!(route_difference(route, other) >= 4, "{table:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_repeated_solutions_once() {
+        let mut solutions = vec![(vec![0, 2, 1, 3], 120)];
+        {
+            let mut emit = emit_top_n_solutions(&mut solutions, 2);
+            assert_eq!(emit(&[0, 1, 2, 3], 100), 120);
+            assert_eq!(emit(&[0, 1, 2, 3], 100), 120);
+            assert_eq!(emit(&[0, 1, 0, 2, 3], 110), 110);
+        }
+        assert_eq!(
+            solutions,
+            [(vec![0, 1, 2, 3], 100), (vec![0, 1, 0, 2, 3], 110)]
+        );
+    }
+
+    /// fastest route if `connections` take no time, and their maps have no other connections
+    fn best_with_new_connections(table: &[Vec<u32>], connections: &[Connection]) -> Option<Time> {
+        let mut table = table.to_vec();
+        for &Connection { from, to } in connections {
+            // restarts stay possible
+            table[from][1..].fill(MISSING);
+            for row in &mut table {
+                row[to] = MISSING;
+            }
+            table[from][to] = 0;
+        }
+        let mut best = None;
+        solve_table(
+            &table,
+            &SolverSettings::default(),
+            emit_only_best(&mut best),
+        )
+        .unwrap();
+        best.map(|(_, time)| time)
+    }
+
+    #[test]
+    fn suggests_connections_which_beat_the_time() {
+        let mut rng = Rng::new(17);
+        let (mut found, mut skipped) = (0, 0);
+        for case in 0..20 {
+            let n = 4 + case % 3;
+            let table = random_table(&mut rng, n, 60);
+            let settings = SolverSettings::default();
+            let mut best = None;
+            solve_table(&table, &settings, emit_only_best(&mut best)).unwrap();
+            // some connections can't beat a time below the best one, so their lower bound skips them
+            let time_to_beat =
+                best.map_or(5000, |(_, time)| time) - if case % 2 == 0 { 0 } else { 100 };
+
+            let search = |threads| {
+                let (mut connections, mut progress) = (Vec::new(), Vec::new());
+                find_new_connections(
+                    &table,
+                    &settings,
+                    time_to_beat,
+                    threads,
+                    |connection| {
+                        let PossibleConnection { start, end, route } = connection;
+                        connections.push((start, end, route.maps, route.time));
+                    },
+                    |tested, total| progress.push((tested, total)),
+                )
+                .unwrap();
+                connections.sort();
+                (connections, progress)
+            };
+            let (connections, progress) = search(1);
+            assert_eq!(search(3), (connections.clone(), progress.clone()));
+
+            // nothing is tested after the finish or into it
+            let missing: Vec<Connection> = (0..n - 1)
+                .flat_map(|from| (1..n - 1).map(move |to| Connection { from, to }))
+                .filter(|&Connection { from, to }| from != to && table[from][to] >= MISSING)
+                .collect();
+            let total = missing.len();
+            assert_eq!(
+                progress,
+                (1..=total)
+                    .map(|tested| (tested, total))
+                    .collect::<Vec<_>>()
+            );
+
+            let files = collect_files(&table);
+            for connection in missing {
+                let expected = best_with_new_connections(&table, &[connection])
+                    .filter(|&time| time <= time_to_beat);
+                let suggested = connections.iter().find(|&&(start, end, ..)| {
+                    Connection {
+                        from: start,
+                        to: end,
+                    } == connection
+                });
+                assert_eq!(
+                    suggested.map(|(.., time)| *time),
+                    expected,
+                    "{connection:?} in {table:?}"
+                );
+
+                let files = edit_files_to_test_new_connections(&files, 0, &[connection]);
+                let problem = Problem::new(&files, n, &settings).unwrap();
+                if problem.root_lower_bound(&settings) > time_to_beat {
+                    assert_eq!(expected, None, "{connection:?} in {table:?}");
+                    skipped += 1;
+                }
+            }
+            found += connections.len();
+        }
+        assert!(found > 0);
+        assert!(skipped > 0);
+    }
+
+    #[test]
+    fn suggests_pairs_which_only_help_together() {
+        let mut rng = Rng::new(18);
+        let mut found = 0;
+        for case in 0..20 {
+            let n = 4 + case % 3;
+            let table = random_table(&mut rng, n, 60);
+            let settings = SolverSettings::default();
+            let mut best = None;
+            solve_table(&table, &settings, emit_only_best(&mut best)).unwrap();
+            let time_to_beat = best.map_or(5000, |(_, time)| time);
+
+            let mut pairs = Vec::new();
+            find_new_connection_pairs(
+                &table,
+                &settings,
+                time_to_beat,
+                2,
+                |pair| pairs.push(pair),
+                |_, _| {},
+            )
+            .unwrap();
+
+            let single = |connection: Connection| {
+                best_with_new_connections(&table, &[connection])
+                    .filter(|&time| time <= time_to_beat)
+            };
+            let is_missing = |from: usize, to: usize| from != to && table[from][to] >= MISSING;
+            // like for single connections, nothing is tested after the finish or into it
+            let connections: Vec<Connection> = (0..n - 1)
+                .flat_map(|from| (1..n - 1).map(move |to| Connection { from, to }))
+                .filter(|connection| is_missing(connection.from, connection.to))
+                .collect();
+            for (i, &first) in connections.iter().enumerate() {
+                for &second in &connections[i + 1..] {
+                    if first.from == second.from
+                        || first.to == second.to
+                        || (first.from == second.to && first.to == second.from)
+                    {
+                        continue;
+                    }
+                    let single_time = single(first).into_iter().chain(single(second)).min();
+                    let together = best_with_new_connections(&table, &[first, second])
+                        .filter(|&time| time <= time_to_beat)
+                        .filter(|&time| single_time.is_none_or(|single_time| time < single_time));
+
+                    let pair = pairs
+                        .iter()
+                        .find(|pair| (pair.first, pair.second) == (first, second));
+                    assert_eq!(
+                        pair.map(|pair| (pair.route.time, pair.single_time)),
+                        together.map(|time| (time, single_time)),
+                        "{first:?} and {second:?} in {table:?}"
+                    );
+                }
+            }
+            found += pairs.len();
+        }
+        assert!(found > 0);
+    }
+
+    #[test]
+    fn cancelling_leaves_a_lower_bound_for_the_routes_not_emitted() {
+        let mut rng = Rng::new(22);
+        let mut stopped = 0;
+        for case in 0..30 {
+            let n = 5 + case % 4;
+            let table = random_table(&mut rng, n, 30);
+            let routes = all_routes(&table, &SolverSettings::default());
+            for (backend, threads, stop_after) in [
+                (Backend::BranchAndBound, 1, 0),
+                (Backend::BranchAndBound, 1, 1),
+                (Backend::BranchAndBound, 1, 3),
+                (Backend::BranchAndBound, 3, 0),
+                (Backend::BranchAndBound, 3, 1),
+                (Backend::HeldKarp, 1, 0),
+            ] {
+                let token = CancellationToken::new();
+                if stop_after == 0 {
+                    token.cancel();
+                }
+                let settings = SolverSettings {
+                    backend,
+                    cancellation: Some(token.clone()),
+                    ..Default::default()
+                };
+                let mut emitted = Vec::new();
+                let stats = solve_table_parallel(&table, &settings, threads, |route, time| {
+                    emitted.push((route.to_vec(), time));
+                    if emitted.len() >= stop_after {
+                        token.cancel();
+                    }
+                    Time::MAX
+                })
+                .unwrap();
+
+                if stop_after == 0 || threads == 1 {
+                    assert_eq!(emitted.len(), stop_after.min(routes.len()));
+                }
+                if emitted.len() < routes.len() {
+                    assert!(!stats.complete);
+                    stopped += 1;
+                }
+                for (route, time) in &routes {
+                    if !emitted.iter().any(|(emitted, _)| emitted == route) {
+                        assert!(*time >= stats.lower_bound, "{table:?} with {settings:?}");
+                    }
+                }
+            }
+        }
+        assert!(stopped > 0);
+    }
+
+    #[test]
+    fn stops_at_the_deadline_with_every_bound() {
+        let mut rng = Rng::new(23);
+        let table = random_table(&mut rng, 120, 70);
+        for lower_bound in LowerBound::ALL {
+            for threads in [1, 2] {
+                let start = Instant::now();
+                let settings = SolverSettings {
+                    lower_bound,
+                    deadline: Some(start + Duration::from_millis(50)),
+                    ..Default::default()
+                };
+                let mut best = None;
+                let stats =
+                    solve_table_parallel(&table, &settings, threads, emit_only_best(&mut best))
+                        .unwrap();
+                // generous, the search checks the clock every few milliseconds
+                assert!(start.elapsed() < Duration::from_secs(1), "{lower_bound:?}");
+                assert!(!stats.complete);
+                if let Some((_, time)) = best {
+                    assert!(stats.lower_bound <= time);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reports_progress_until_everything_is_explored() {
+        let mut rng = Rng::new(24);
+        for case in 0..10 {
+            let n = 9 + case % 3;
+            let table = random_table(&mut rng, n, 30);
+            for threads in [1, 3] {
+                let reports = Arc::new(Mutex::new(Vec::new()));
+                let settings = SolverSettings {
+                    progress: Some(ProgressReporter::new(20, {
+                        let reports = Arc::clone(&reports);
+                        move |progress| reports.lock().unwrap().push(*progress)
+                    })),
+                    ..Default::default()
+                };
+                let mut best = None;
+                let stats =
+                    solve_table_parallel(&table, &settings, threads, emit_only_best(&mut best))
+                        .unwrap();
+                assert!(stats.complete);
+                let best_time = best.map(|(_, time)| time);
+
+                let reports = reports.lock().unwrap();
+                assert!(reports.len() > 1, "{table:?}");
+                for pair in reports.windows(2) {
+                    assert!(pair[0].explored <= pair[1].explored, "{pair:?}");
+                    assert!(pair[0].iterations <= pair[1].iterations);
+                }
+                for progress in reports.iter() {
+                    assert!(progress.lower_bound <= best_time.unwrap_or(Time::MAX));
+                    assert!(progress
+                        .best_time
+                        .is_none_or(|time| Some(time) >= best_time));
+                }
+                let last = reports.last().unwrap();
+                assert_eq!(last.explored, 1.0);
+                assert_eq!(last.best_time, best_time);
+                assert_eq!(last.iterations, stats.iterations);
+            }
         }
     }
 }
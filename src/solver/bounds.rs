@@ -0,0 +1,278 @@
+//! Lower bounds for the time still needed to visit every remaining map.
+//!
+//! All bounds relax restarts to a single edge `from -> 0 -> to` costing `restart_time(from) + time(0, to)`,
+//! which is available from every node that can restart as long as the restart limit isn't reached yet.
+//! Leaving out an optional map is relaxed to a connection from the map to itself costing its skip penalty,
+//! which covers both its incoming and its outgoing connection.
+
+use super::{NodeIdx, Problem, Time};
+
+/// stand-in for missing connections, big enough to never be part of a feasible bound
+const MISSING: i64 = 1 << 40;
+
+pub(super) struct BoundState<'a> {
+    pub pos: NodeIdx,
+    pub can_go: &'a [bool],
+    pub restarts_allowed: bool,
+}
+
+impl BoundState<'_> {
+    /// every node which can still be entered, including the finish
+    fn remaining(&self, problem: &Problem) -> Vec<NodeIdx> {
+        (0..problem.n)
+            .filter(|&node| self.can_go[node] && node != problem.start)
+            .collect()
+    }
+
+    /// cheapest way to get from `from` to `to`, either directly or by restarting
+    fn cost(&self, problem: &Problem, from: NodeIdx, to: NodeIdx) -> i64 {
+        if from == to {
+            return match problem.skip_penalties[from] {
+                Some(skip_penalty) => skip_penalty as i64,
+                None => MISSING,
+            };
+        }
+
+        let direct = edge(problem, from, to);
+        if from == problem.start {
+            return direct;
+        }
+
+        let restart = match problem.restart_time(from) {
+            Some(restart_time) if self.restarts_allowed => {
+                restart_time as i64 + edge(problem, problem.start, to)
+            }
+            _ => MISSING,
+        };
+        direct.min(restart)
+    }
+
+    /// every node which can still be left, the current position and everything remaining except the finish
+    fn sources(&self, problem: &Problem, remaining: &[NodeIdx]) -> Vec<NodeIdx> {
+        std::iter::once(self.pos)
+            .chain(remaining.iter().copied())
+            .filter(|&node| node != problem.finish)
+            .collect()
+    }
+}
+
+fn edge(problem: &Problem, from: NodeIdx, to: NodeIdx) -> i64 {
+    match problem.matrix[from * problem.n + to] {
+        Time::MAX => MISSING,
+        time => time as i64,
+    }
+}
+
+fn to_time(bound: i64) -> Time {
+    bound.min(Time::MAX as i64) as Time
+}
+
+/// sum of the cheapest incoming connection of every remaining node, only counting connections from nodes which can still be left
+pub(super) fn available_incoming(problem: &Problem, state: &BoundState) -> Time {
+    let remaining = state.remaining(problem);
+    let sources = state.sources(problem, &remaining);
+
+    let bound = remaining
+        .iter()
+        .map(|&to| {
+            sources
+                .iter()
+                .map(|&from| state.cost(problem, from, to))
+                .min()
+                .unwrap_or(MISSING)
+        })
+        .sum();
+    to_time(bound)
+}
+
+/// sum of the cheapest outgoing connection of every node which still has to be left, only counting connections to remaining nodes
+pub(super) fn available_outgoing(problem: &Problem, state: &BoundState) -> Time {
+    let remaining = state.remaining(problem);
+    let sources = state.sources(problem, &remaining);
+
+    let bound = sources
+        .iter()
+        .map(|&from| {
+            remaining
+                .iter()
+                .map(|&to| state.cost(problem, from, to))
+                .min()
+                .unwrap_or(MISSING)
+        })
+        .sum();
+    to_time(bound)
+}
+
+/// Every node which still has to be left is assigned a distinct remaining successor.
+/// The cheapest such assignment (solved using the hungarian algorithm) is a lower bound for the rest of the route.
+pub(super) fn assignment(problem: &Problem, state: &BoundState) -> Time {
+    let remaining = state.remaining(problem);
+    let sources = state.sources(problem, &remaining);
+    debug_assert_eq!(sources.len(), remaining.len());
+
+    let k = remaining.len();
+    if k == 0 {
+        return 0;
+    }
+
+    // 1-indexed potentials formulation, see https://e-maxx.ru/algo/assignment_hungary
+    let cost = |row: usize, col: usize| state.cost(problem, sources[row - 1], remaining[col - 1]);
+    let mut u = vec![0i64; k + 1];
+    let mut v = vec![0i64; k + 1];
+    let mut matched_row = vec![0usize; k + 1];
+    let mut way = vec![0usize; k + 1];
+
+    for row in 1..=k {
+        matched_row[0] = row;
+        let mut col0 = 0;
+        let mut min_v = vec![i64::MAX; k + 1];
+        let mut used = vec![false; k + 1];
+
+        loop {
+            used[col0] = true;
+            let row0 = matched_row[col0];
+            let mut delta = i64::MAX;
+            let mut col1 = 0;
+
+            for col in 1..=k {
+                if used[col] {
+                    continue;
+                }
+                let current = cost(row0, col) - u[row0] - v[col];
+                if current < min_v[col] {
+                    min_v[col] = current;
+                    way[col] = col0;
+                }
+                if min_v[col] < delta {
+                    delta = min_v[col];
+                    col1 = col;
+                }
+            }
+
+            for col in 0..=k {
+                if used[col] {
+                    u[matched_row[col]] += delta;
+                    v[col] -= delta;
+                } else {
+                    min_v[col] -= delta;
+                }
+            }
+
+            col0 = col1;
+            if matched_row[col0] == 0 {
+                break;
+            }
+        }
+
+        loop {
+            let col1 = way[col0];
+            matched_row[col0] = matched_row[col1];
+            col0 = col1;
+            if col0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let bound = (1..=k).map(|col| cost(matched_row[col], col)).sum();
+    to_time(bound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assignment, available_incoming, available_outgoing, BoundState};
+    use crate::solver::{collect_files, solve_table, LowerBound, Problem, SolverSettings};
+    use crate::testing::{all_routes, random_table, settings_variants, Rng};
+
+    #[test]
+    fn never_exceed_the_rest_of_a_route() {
+        let mut rng = Rng::new(4);
+        for case in 0..100 {
+            let n = 4 + case % 3;
+            let table = random_table(&mut rng, n, 30);
+            for settings in settings_variants(n) {
+                let problem = Problem::new(&collect_files(&table), n, &settings).unwrap();
+                for (route, route_time) in all_routes(&table, &settings) {
+                    let trail = problem.numbering.to_internal(&route).unwrap();
+                    let mut can_go = vec![true; problem.n];
+                    let mut time = 0;
+                    let mut restarts = 0;
+
+                    for (i, &pos) in trail.iter().enumerate() {
+                        if i > 0 {
+                            let from = trail[i - 1];
+                            if pos == problem.start {
+                                time += problem.restart_times[from];
+                                restarts += 1;
+                            } else {
+                                time += problem.matrix[from * problem.n + pos];
+                                can_go[pos] = false;
+                            }
+                        }
+                        let state = BoundState {
+                            pos,
+                            can_go: &can_go,
+                            restarts_allowed: settings
+                                .max_restarts
+                                .is_none_or(|max_restarts| restarts < max_restarts),
+                        };
+                        let cheapest_incoming: u32 = (0..problem.n)
+                            .filter(|&node| can_go[node])
+                            .map(|node| problem.lowest_times[node])
+                            .sum();
+                        let bounds = [
+                            cheapest_incoming,
+                            available_incoming(&problem, &state),
+                            available_outgoing(&problem, &state),
+                            assignment(&problem, &state),
+                        ];
+                        for bound in bounds {
+                            assert!(
+                                time + bound <= route_time,
+                                "bound {bound} after {:?} of {route:?} in {table:?} with {settings:?}",
+                                &trail[..=i]
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn find_the_same_routes() {
+        let mut rng = Rng::new(5);
+        for case in 0..100 {
+            let n = 4 + case % 5;
+            let table = random_table(&mut rng, n, 20);
+            for settings in settings_variants(n) {
+                // every route as fast as the fastest one found so far, to compare ties as well
+                let fastest_routes = |lower_bound| {
+                    let settings = SolverSettings {
+                        lower_bound,
+                        ..settings.clone()
+                    };
+                    let mut routes: Vec<(Vec<usize>, u32)> = Vec::new();
+                    solve_table(&table, &settings, |route, time| {
+                        routes.retain(|&(_, kept)| kept <= time);
+                        if routes.first().is_none_or(|&(_, fastest)| time <= fastest) {
+                            routes.push((route.to_vec(), time));
+                        }
+                        routes[0].1 + 1
+                    })
+                    .unwrap();
+                    routes.sort_unstable();
+                    routes
+                };
+                let expected = fastest_routes(LowerBound::CheapestIncoming);
+                for lower_bound in LowerBound::ALL {
+                    assert_eq!(
+                        fastest_routes(lower_bound),
+                        expected,
+                        "{lower_bound:?} in {table:?} with {settings:?}"
+                    );
+                }
+            }
+        }
+    }
+}
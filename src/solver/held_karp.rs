@@ -0,0 +1,456 @@
+//! Exact bitmask dynamic programming over (visited set, current node, restarts used).
+//!
+//! The table computes the fastest way to finish the route from every state,
+//! which is then used as an exact bound to enumerate the best solutions in a depth first search.
+
+use super::{NodeIdx, Problem, Progress, SolverSettings, Stats, Time, STOP_CHECK_INTERVAL};
+
+/// Upper limit for the number of entries in the table (256MB of times), bigger problems use the branch and bound search.
+pub(super) const MAX_TABLE_SIZE: usize = 1 << 26;
+
+const UNREACHABLE: Time = Time::MAX;
+
+pub(super) fn table_size(problem: &Problem, settings: &SolverSettings) -> Option<usize> {
+    let places = problem.n.checked_sub(2)?;
+    let masks = 1usize.checked_shl(places.try_into().ok()?)?;
+    let restart_layers = restart_layers(settings);
+
+    masks
+        .checked_mul(problem.n - 1)?
+        .checked_mul(restart_layers)
+        .filter(|&size| size <= MAX_TABLE_SIZE)
+}
+
+/// with unlimited restarts the amount of restarts doesn't need to be tracked
+fn restart_layers(settings: &SolverSettings) -> usize {
+    match settings.max_restarts {
+        Some(max_restarts) => max_restarts as usize + 1,
+        None => 1,
+    }
+}
+
+struct HeldKarp<'a, F> {
+    problem: &'a Problem,
+    settings: &'a SolverSettings,
+
+    restart_layers: usize,
+    full_mask: usize,
+    /// every node which has to be visited
+    required_mask: usize,
+    /// `remaining[(restarts * masks + mask) * (n - 1) + node]`: fastest time to the finish
+    remaining: Vec<Time>,
+
+    trail: Vec<NodeIdx>,
+    worst_time_of_interest: Time,
+    /// the worst time of interest never goes above this, see [`super::WarmStart::time`]
+    time_bound: Time,
+    emit_solution: F,
+
+    iterations: u32,
+    solutions_found: u32,
+    cut_branches: u32,
+    stopped: bool,
+}
+
+pub(super) fn solve<F>(problem: &Problem, settings: &SolverSettings, emit_solution: F) -> Stats
+where
+    F: FnMut(&[NodeIdx], Time) -> Time,
+{
+    let size = table_size(problem, settings).expect("problem too large for held-karp");
+    let places = problem.n - 2;
+    let time_bound = super::warm_start::time_bound(settings).unwrap_or(Time::MAX);
+
+    let mut cx = HeldKarp {
+        problem,
+        settings,
+        restart_layers: restart_layers(settings),
+        full_mask: (1 << places) - 1,
+        required_mask: (1..=places)
+            .filter(|&node| !problem.is_optional(node))
+            .fold(0, |mask, node| mask | 1 << (node - 1)),
+        remaining: vec![UNREACHABLE; size],
+        trail: Vec::with_capacity(problem.n * 2),
+        worst_time_of_interest: time_bound,
+        time_bound,
+        emit_solution,
+        iterations: 0,
+        solutions_found: 0,
+        cut_branches: 0,
+        stopped: false,
+    };
+    cx.fill_table();
+    if cx.stopped {
+        return Stats {
+            complete: false,
+            lower_bound: 0,
+            ..Default::default()
+        };
+    }
+
+    cx.trail.push(problem.start);
+    cx.enumerate(problem.start, 0, 0, 0);
+
+    if let Some(progress) = settings.progress.as_ref().filter(|_| !cx.stopped) {
+        progress.report(&Progress {
+            explored: 1.0,
+            ..Default::default()
+        });
+    }
+
+    // the table contains the time of the fastest route, which bounds everything not enumerated yet
+    let fastest = cx.remaining(0, problem.start, 0);
+    Stats {
+        iterations: cx.iterations,
+        solutions_found: cx.solutions_found,
+        cut_branches: cx.cut_branches,
+        complete: !cx.stopped,
+        lower_bound: match cx.stopped {
+            true => cx.worst_time_of_interest.min(fastest),
+            false => cx.worst_time_of_interest,
+        },
+        ..Default::default()
+    }
+}
+
+impl<F> HeldKarp<'_, F>
+where
+    F: FnMut(&[NodeIdx], Time) -> Time,
+{
+    fn bit(&self, node: NodeIdx) -> Option<usize> {
+        (node != self.problem.start && node != self.problem.finish).then(|| 1 << (node - 1))
+    }
+
+    /// whether the precedences allow visiting `node` after visiting `mask`
+    fn allowed(&self, mask: usize, node: NodeIdx) -> bool {
+        self.problem.can_visit(node, |before| {
+            self.bit(before).is_some_and(|bit| mask & bit != 0)
+        })
+    }
+
+    fn index(&self, mask: usize, node: NodeIdx, restarts: usize) -> usize {
+        ((restarts * (self.full_mask + 1)) + mask) * (self.problem.n - 1) + node
+    }
+
+    fn remaining(&self, mask: usize, node: NodeIdx, restarts: usize) -> Time {
+        self.remaining[self.index(mask, node, restarts)]
+    }
+
+    fn restart_count(&self, restarts: usize) -> Option<usize> {
+        match self.settings.max_restarts {
+            Some(_) => (restarts + 1 < self.restart_layers).then_some(restarts + 1),
+            None => Some(0),
+        }
+    }
+
+    /// returns the restart layer after restarting at `node`, if that's allowed
+    fn can_restart(&self, mask: usize, node: NodeIdx, restarts: usize) -> Option<usize> {
+        if node == self.problem.start || self.problem.restart_time(node).is_none() {
+            return None;
+        }
+
+        if self.settings.only_required_restarts {
+            let must = self.problem.nodes[node].targets.iter().all(|&target| {
+                self.bit(target).is_some_and(|bit| mask & bit != 0) || !self.allowed(mask, target)
+            });
+            if !must {
+                return None;
+            }
+        }
+
+        self.restart_count(restarts)
+    }
+
+    /// penalty for the nodes left out of `mask`, `None` if it's missing required nodes
+    fn skip_penalty(&self, mask: usize) -> Option<Time> {
+        if mask == self.full_mask {
+            return Some(0);
+        }
+        if mask & self.required_mask != self.required_mask {
+            return None;
+        }
+        self.problem
+            .skip_penalty(|node| self.bit(node).is_some_and(|bit| mask & bit != 0))
+    }
+
+    /// successors of `node` after visiting `mask`, with their new mask and time
+    fn successors(
+        &self,
+        mask: usize,
+        node: NodeIdx,
+    ) -> impl Iterator<Item = (NodeIdx, usize, Time)> + '_ {
+        let place = &self.problem.nodes[node];
+        place
+            .targets
+            .iter()
+            .zip(place.times.iter())
+            .filter(move |&(&target, _)| self.allowed(mask, target))
+            .filter_map(move |(&target, &time)| match self.bit(target) {
+                Some(bit) if mask & bit == 0 => Some((target, mask | bit, time)),
+                Some(_) => None,
+                None => (target == self.problem.finish)
+                    .then(|| self.skip_penalty(mask))
+                    .flatten()
+                    .map(|skip_penalty| (target, mask, time.saturating_add(skip_penalty))),
+            })
+    }
+
+    fn fill_table(&mut self) {
+        let finish = self.problem.finish;
+
+        for mask in (0..=self.full_mask).rev() {
+            if mask.is_multiple_of(STOP_CHECK_INTERVAL as usize) {
+                if self.settings.should_stop() {
+                    self.stopped = true;
+                    return;
+                }
+
+                // filling the table takes most of the time, the enumeration afterwards is quick
+                if let Some(progress) = &self.settings.progress {
+                    progress.report(&Progress {
+                        explored: (self.full_mask - mask) as f64 / (self.full_mask + 1) as f64,
+                        ..Default::default()
+                    });
+                }
+            }
+
+            // the start node only depends on bigger masks, every other node may restart to the start node
+            let nodes: Vec<_> = std::iter::once(self.problem.start)
+                .chain((1..finish).filter(|&node| mask & (1 << (node - 1)) != 0))
+                .collect();
+            for node in nodes {
+                for restarts in (0..self.restart_layers).rev() {
+                    let mut best = UNREACHABLE;
+
+                    for (target, new_mask, time) in self.successors(mask, node) {
+                        let rest = if target == finish {
+                            0
+                        } else {
+                            self.remaining(new_mask, target, restarts)
+                        };
+                        best = best.min(time.saturating_add(rest));
+                    }
+
+                    if let Some(new_restarts) = self.can_restart(mask, node, restarts) {
+                        let rest = self.remaining(mask, self.problem.start, new_restarts);
+                        let restart_time = self.problem.restart_times[node];
+                        best = best.min(restart_time.saturating_add(rest));
+                    }
+
+                    let index = self.index(mask, node, restarts);
+                    self.remaining[index] = best;
+                }
+            }
+        }
+    }
+
+    fn enumerate(&mut self, pos: NodeIdx, mask: usize, restarts: usize, time: Time) {
+        self.iterations += 1;
+
+        if self.iterations.is_multiple_of(STOP_CHECK_INTERVAL) && self.settings.should_stop() {
+            self.stopped = true;
+        }
+        if self.stopped {
+            return;
+        }
+
+        if pos == self.problem.finish {
+            if time >= self.time_bound {
+                return;
+            }
+            self.solutions_found += 1;
+            let solution = self.problem.numbering.to_original(&self.trail);
+            self.worst_time_of_interest =
+                (self.emit_solution)(&solution, time).min(self.time_bound);
+            return;
+        }
+
+        let bound = time.saturating_add(self.remaining(mask, pos, restarts));
+        if bound >= self.worst_time_of_interest {
+            self.cut_branches += 1;
+            return;
+        }
+
+        let successors: Vec<_> = self.successors(mask, pos).collect();
+        for (target, new_mask, added_time) in successors {
+            self.trail.push(target);
+            self.enumerate(target, new_mask, restarts, time + added_time);
+            self.trail.pop();
+        }
+
+        if let Some(new_restarts) = self.can_restart(mask, pos, restarts) {
+            self.trail.push(self.problem.start);
+            let time = time + self.problem.restart_times[pos];
+            self.enumerate(self.problem.start, mask, new_restarts, time);
+            self.trail.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::solver::{
+        emit_top_n_solutions, solve_table, Backend, Connection, OptionalMap, OptionalMaps,
+        Precedence, SolverSettings,
+    };
+    use crate::testing::{all_routes, brute_force_routes, random_table, settings_variants, Rng};
+    use crate::MISSING;
+
+    fn held_karp(settings: &SolverSettings) -> SolverSettings {
+        SolverSettings {
+            backend: Backend::HeldKarp,
+            ..settings.clone()
+        }
+    }
+
+    #[test]
+    fn finds_the_same_routes_as_branch_and_bound() {
+        let mut rng = Rng::new(2);
+        for case in 0..100 {
+            let n = 4 + case % 4;
+            let table = random_table(&mut rng, n, 30);
+            for settings in settings_variants(n) {
+                assert_eq!(
+                    all_routes(&table, &held_karp(&settings)),
+                    all_routes(&table, &settings),
+                    "{table:?} with {settings:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn finds_the_same_routes_as_brute_force() {
+        let mut rng = Rng::new(25);
+        for case in 0..60 {
+            let n = 4 + case % 3;
+            let table = random_table(&mut rng, n, 30);
+            for settings in settings_variants(n) {
+                let routes = brute_force_routes(&table, &settings);
+                assert_eq!(
+                    all_routes(&table, &settings),
+                    routes,
+                    "{table:?} with {settings:?}"
+                );
+                assert_eq!(all_routes(&table, &held_karp(&settings)), routes);
+            }
+        }
+    }
+
+    /// 0-1-2-3-4 is the fastest route, in 400 frames
+    fn fixed_table() -> Vec<Vec<u32>> {
+        const M: u32 = MISSING;
+        vec![
+            vec![0, 100, 300, 300, M],
+            vec![500, 0, 100, 300, M],
+            vec![500, 300, 0, 100, 150],
+            vec![500, 300, 300, 0, 100],
+            vec![500, M, M, M, 0],
+        ]
+    }
+
+    /// every route of both backends, which have to be the ones brute force finds
+    fn routes(table: &[Vec<u32>], settings: &SolverSettings) -> Vec<(Vec<usize>, u32)> {
+        let routes = brute_force_routes(table, settings);
+        assert_eq!(all_routes(table, settings), routes, "{settings:?}");
+        assert_eq!(
+            all_routes(table, &held_karp(settings)),
+            routes,
+            "{settings:?}"
+        );
+        routes
+    }
+
+    #[test]
+    fn a_precedence_changes_the_best_route() {
+        let table = fixed_table();
+        assert_eq!(
+            routes(&table, &SolverSettings::default())[0],
+            (vec![0, 1, 2, 3, 4], 400)
+        );
+
+        let settings = SolverSettings {
+            precedences: vec![Precedence {
+                before: 3,
+                after: 2,
+            }],
+            ..Default::default()
+        };
+        let routes = routes(&table, &settings);
+        assert_eq!(routes[0].1, 850);
+        for (route, _) in &routes {
+            let position = |map| route.iter().position(|&other| other == map);
+            assert!(position(3) < position(2), "{route:?}");
+        }
+    }
+
+    #[test]
+    fn avoids_a_forbidden_connection_of_the_best_route() {
+        let table = fixed_table();
+        let settings = SolverSettings {
+            forbidden_connections: vec![Connection { from: 2, to: 3 }],
+            ..Default::default()
+        };
+        let routes = routes(&table, &settings);
+        assert_eq!(routes[0], (vec![0, 1, 3, 2, 4], 850));
+        for (route, _) in &routes {
+            assert!(!route.windows(2).any(|pair| pair == [2, 3]), "{route:?}");
+        }
+    }
+
+    #[test]
+    fn skips_an_optional_map_below_its_penalty() {
+        let table = fixed_table();
+        // 0-1-2-4 takes 350 frames, so leaving out map 3 pays off below a penalty of 50
+        let best_with_penalty = |skip_penalty| {
+            let settings = SolverSettings {
+                optional_maps: OptionalMaps {
+                    maps: vec![OptionalMap {
+                        map: 3,
+                        skip_penalty,
+                    }],
+                    min_visited: 0,
+                },
+                ..Default::default()
+            };
+            let routes = routes(&table, &settings);
+            let best_time = routes[0].1;
+            let best: Vec<_> = routes
+                .into_iter()
+                .take_while(|&(_, time)| time == best_time)
+                .map(|(route, _)| route)
+                .collect();
+            (best, best_time)
+        };
+
+        assert_eq!(best_with_penalty(49), (vec![vec![0, 1, 2, 4]], 399));
+        assert_eq!(
+            best_with_penalty(50),
+            (vec![vec![0, 1, 2, 3, 4], vec![0, 1, 2, 4]], 400)
+        );
+        assert_eq!(best_with_penalty(51), (vec![vec![0, 1, 2, 3, 4]], 400));
+    }
+
+    #[test]
+    fn finds_the_same_top_times_as_branch_and_bound() {
+        let mut rng = Rng::new(3);
+        for case in 0..100 {
+            let n = 4 + case % 5;
+            let table = random_table(&mut rng, n, 20);
+            for settings in settings_variants(n) {
+                let top_times = |settings: &SolverSettings| {
+                    let mut solutions = Vec::new();
+                    solve_table(&table, settings, emit_top_n_solutions(&mut solutions, 5)).unwrap();
+                    solutions
+                        .into_iter()
+                        .map(|(_, time)| time)
+                        .collect::<Vec<_>>()
+                };
+                assert_eq!(
+                    top_times(&held_karp(&settings)),
+                    top_times(&settings),
+                    "{table:?} with {settings:?}"
+                );
+            }
+        }
+    }
+}
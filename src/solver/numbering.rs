@@ -0,0 +1,197 @@
+//! Translation between the node numbers of the table and the ones used by the search.
+//!
+//! The search always starts at node `0` and finishes at the last node, so other start and finish nodes are moved there.
+//! Routes which may end at several nodes get an extra finish node instead, which each of them connects to for free.
+//! Chains of forced connections are collapsed into a single node, which is entered at the first node of the chain
+//! and left at the last one, and expanded again in emitted routes.
+
+use super::{Connection, FileInfo, Finish, NodeIdx, SolverSettings, Time};
+use std::borrow::Cow;
+
+pub(super) struct Numbering {
+    /// nodes of the table each node of the search stands for, in the order they are visited.
+    /// Empty for the extra finish node.
+    original: Vec<Vec<NodeIdx>>,
+    /// node of the search for each node of the table
+    internal: Vec<NodeIdx>,
+    /// nodes of the search which connect to the extra finish node, if there is one
+    finishes: Vec<NodeIdx>,
+    forbidden: Vec<Connection>,
+    /// whether the table can be searched as it is
+    identity: bool,
+}
+
+impl Numbering {
+    /// numbering for a table with `n` nodes, which has to pass [`SolverSettings::check`]
+    pub(super) fn new(n: usize, settings: &SolverSettings) -> Numbering {
+        let start = settings.start;
+        let finish = settings.finish.single(n);
+
+        let mut next = vec![None; n];
+        let mut has_previous = vec![false; n];
+        for connection in &settings.forced_connections {
+            next[connection.from] = Some(connection.to);
+            has_previous[connection.to] = true;
+        }
+        let chains = (0..n)
+            .filter(|&node| !has_previous[node])
+            .map(|head| std::iter::successors(Some(head), |&node| next[node]).collect::<Vec<_>>());
+        let (mut finish_chain, mut chains): (Vec<Vec<_>>, Vec<_>) =
+            chains.partition(|chain: &Vec<_>| finish.is_some_and(|finish| chain.contains(&finish)));
+
+        // the start is never part of a forced connection
+        let start_chain = chains.iter().position(|chain| chain[0] == start).unwrap();
+        let mut original = vec![chains.remove(start_chain)];
+        original.append(&mut chains);
+        original.push(finish_chain.pop().unwrap_or_default());
+
+        let mut internal = vec![0; n];
+        for (node, chain) in original.iter().enumerate() {
+            for &original in chain {
+                internal[original] = node;
+            }
+        }
+
+        let extra_finish = original.len() - 1;
+        let finishes = match (&settings.finish, finish) {
+            (_, Some(_)) | (Finish::Last, _) => Vec::new(),
+            (Finish::Nodes(finishes), None) => {
+                // routes can't finish in the middle of a chain
+                let mut finishes: Vec<_> = finishes
+                    .iter()
+                    .map(|&node| internal[node])
+                    .filter(|&node| finishes.contains(original[node].last().unwrap()))
+                    .collect();
+                finishes.sort_unstable();
+                finishes.dedup();
+                finishes
+            }
+            (Finish::Anywhere, None) => (1..extra_finish).collect(),
+        };
+
+        let identity = settings.forbidden_connections.is_empty()
+            && original
+                .iter()
+                .enumerate()
+                .all(|(node, chain)| chain[..] == [node]);
+
+        Numbering {
+            original,
+            internal,
+            finishes,
+            forbidden: settings.forbidden_connections.clone(),
+            identity,
+        }
+    }
+
+    /// amount of nodes of the search
+    pub(super) fn len(&self) -> usize {
+        self.original.len()
+    }
+
+    pub(super) fn node(&self, original: NodeIdx) -> NodeIdx {
+        self.internal[original]
+    }
+
+    /// nodes of the table `node` stands for
+    pub(super) fn chain(&self, node: NodeIdx) -> &[NodeIdx] {
+        &self.original[node]
+    }
+
+    /// node of the search which is left at `original`, if `original` isn't followed by a forced connection
+    pub(super) fn exit(&self, original: NodeIdx) -> Option<NodeIdx> {
+        let node = self.internal[original];
+        (self.original[node].last() == Some(&original)).then_some(node)
+    }
+
+    /// node of the search which is entered at `original`, if `original` isn't preceded by a forced connection
+    fn entry(&self, original: NodeIdx) -> Option<NodeIdx> {
+        let node = self.internal[original];
+        (self.original[node].first() == Some(&original)).then_some(node)
+    }
+
+    /// `files` with the numbering of the search, including the connections to the extra finish node.
+    /// The time of a chain is added to the connections entering it.
+    pub(super) fn files<'a>(&self, files: &'a [FileInfo]) -> Cow<'a, [FileInfo]> {
+        if self.identity {
+            return Cow::Borrowed(files);
+        }
+
+        let time = |from: NodeIdx, to: NodeIdx| {
+            files
+                .iter()
+                .find(|file| file.start == from && file.end == to)
+                .map(|file| file.time)
+        };
+        // `None` if a forced connection is missing from the table, which makes the chain unreachable
+        let chain_times: Vec<Option<Time>> = self
+            .original
+            .iter()
+            .map(|chain| {
+                chain
+                    .windows(2)
+                    .try_fold(0, |total: Time, pair| Some(total + time(pair[0], pair[1])?))
+            })
+            .collect();
+
+        let finish = self.len() - 1;
+        let files = files
+            .iter()
+            .filter(|file| {
+                !self.forbidden.contains(&Connection {
+                    from: file.start,
+                    to: file.end,
+                })
+            })
+            .filter_map(|file| {
+                let from = self.exit(file.start)?;
+                let to = self.entry(file.end)?;
+                (from != to).then_some(())?;
+                Some(FileInfo {
+                    start: from,
+                    end: to,
+                    time: file.time + chain_times[to]?,
+                })
+            })
+            .chain(self.finishes.iter().map(|&node| FileInfo {
+                start: node,
+                end: finish,
+                time: 0,
+            }));
+        Cow::Owned(files.collect())
+    }
+
+    /// route of the search in the numbering of the table, with the chains expanded and without the extra finish node
+    pub(super) fn to_original<'a>(&self, trail: &'a [NodeIdx]) -> Cow<'a, [NodeIdx]> {
+        if self.identity {
+            return Cow::Borrowed(trail);
+        }
+        Cow::Owned(
+            trail
+                .iter()
+                .flat_map(|&node| self.original[node].iter().copied())
+                .collect(),
+        )
+    }
+
+    /// Route of the table in the numbering of the search.
+    /// Returns `None` if it contains nodes outside of the table or breaks a chain of forced connections.
+    pub(super) fn to_internal(&self, route: &[NodeIdx]) -> Option<Vec<NodeIdx>> {
+        let mut trail = Vec::with_capacity(route.len() + 1);
+        let mut rest = route;
+        while let Some(&first) = rest.first() {
+            let node = *self.internal.get(first)?;
+            let chain = &self.original[node];
+            if rest.get(..chain.len())? != chain {
+                return None;
+            }
+            trail.push(node);
+            rest = &rest[chain.len()..];
+        }
+
+        if !self.finishes.is_empty() {
+            trail.push(self.len() - 1);
+        }
+        Some(trail)
+    }
+}
@@ -0,0 +1,532 @@
+//! Heuristic routes used to seed the pruning bound before the exact search.
+//!
+//! Routes are built by nearest neighbour and improved by 2-opt and Or-opt moves over the order of the places.
+//! For a fixed order the restarts are placed optimally, so the local search works on routes with restarts as well.
+//! Optional places start out in the order and are left out while that makes the route faster.
+
+use super::{NodeIdx, Problem, SolverSettings, Time};
+
+/// Budget of route evaluations, scaled down for bigger problems so the warm start stays quick
+const EVALUATION_BUDGET: usize = 1 << 24;
+/// Amount of steps the initial route construction may take, including the ones undone by backtracking
+const CONSTRUCTION_BUDGET: usize = 1 << 16;
+
+/// Routes known before the search, in the numbering of the search and with at most `warm_start.solutions` of them.
+///
+/// The solvers emit them like every other solution, so the bound the search starts with is whatever the caller's
+/// collector returns for them, and never prunes a route the collector would keep.
+pub(super) fn known_routes(
+    problem: &Problem,
+    settings: &SolverSettings,
+) -> Vec<(Vec<NodeIdx>, Time)> {
+    let warm_start = &settings.warm_start;
+    let mut known = KnownRoutes {
+        routes: Vec::new(),
+        wanted: warm_start.solutions.max(1),
+    };
+
+    for route in &warm_start.routes {
+        let Some(trail) = problem.numbering.to_internal(route) else {
+            continue;
+        };
+        if let Some(time) = evaluate_trail(problem, settings, &trail) {
+            known.insert(&trail, time);
+        }
+    }
+
+    if warm_start.heuristic {
+        let mut search = LocalSearch {
+            problem,
+            settings,
+            known: &mut known,
+            budget: EVALUATION_BUDGET / problem.n.max(1),
+        };
+        search.run();
+    }
+
+    known.routes
+}
+
+/// Bound from the bare time of the warm start, which the search never goes above.
+///
+/// Routes slower than the time are never interesting, so the bound stays exact for any amount of solutions.
+pub(super) fn time_bound(settings: &SolverSettings) -> Option<Time> {
+    Some(settings.warm_start.time?.saturating_add(1))
+}
+
+/// the fastest distinct routes seen so far
+struct KnownRoutes {
+    routes: Vec<(Vec<NodeIdx>, Time)>,
+    wanted: usize,
+}
+
+impl KnownRoutes {
+    /// time of the slowest route needed to fill the top-N, if there are enough routes
+    fn worst(&self) -> Option<Time> {
+        (self.routes.len() >= self.wanted).then(|| self.routes[self.wanted - 1].1)
+    }
+
+    fn is_interesting(&self, time: Time) -> bool {
+        self.worst().is_none_or(|worst| time < worst)
+    }
+
+    fn insert(&mut self, trail: &[NodeIdx], time: Time) {
+        if !self.is_interesting(time) || self.routes.iter().any(|(route, _)| route == trail) {
+            return;
+        }
+
+        let index = self.routes.partition_point(|&(_, t)| t <= time);
+        self.routes.insert(index, (trail.to_vec(), time));
+        self.routes.truncate(self.wanted);
+    }
+}
+
+/// Time of a complete route like `[0, 3, 1, 0, 2, 4]`, where `0` after the first node is a restart.
+/// Returns `None` if the route isn't possible with the given settings.
+fn evaluate_trail(problem: &Problem, settings: &SolverSettings, trail: &[NodeIdx]) -> Option<Time> {
+    let (&first, rest) = trail.split_first()?;
+    if first != problem.start || rest.last() != Some(&problem.finish) {
+        return None;
+    }
+
+    let mut visited = vec![false; problem.n];
+    let mut time: Time = 0;
+    let mut restarts = 0;
+    let mut pos = first;
+
+    for &next in rest {
+        if next >= problem.n {
+            return None;
+        }
+
+        if next == problem.start {
+            if pos == problem.start || !can_restart_after(problem, settings, &visited, pos) {
+                return None;
+            }
+            restarts += 1;
+            time = time.checked_add(problem.restart_time(pos)?)?;
+        } else {
+            if visited[next] || !problem.can_visit(next, |before| visited[before]) {
+                return None;
+            }
+            visited[next] = true;
+            time = time.checked_add(edge(problem, pos, next)?)?;
+        }
+        pos = next;
+    }
+
+    let skip_penalty = problem.skip_penalty(|node| visited[node])?;
+    let restarts_ok = settings.max_restarts.is_none_or(|max| restarts <= max);
+    restarts_ok.then(|| time.saturating_add(skip_penalty))
+}
+
+fn edge(problem: &Problem, from: NodeIdx, to: NodeIdx) -> Option<Time> {
+    match problem.matrix[from * problem.n + to] {
+        Time::MAX => None,
+        time => Some(time),
+    }
+}
+
+/// with only required restarts, restarting is only allowed once no target of `pos` can be visited anymore
+fn can_restart_after(
+    problem: &Problem,
+    settings: &SolverSettings,
+    visited: &[bool],
+    pos: NodeIdx,
+) -> bool {
+    !settings.only_required_restarts
+        || problem.nodes[pos]
+            .targets
+            .iter()
+            .all(|&target| visited[target] || !problem.can_visit(target, |before| visited[before]))
+}
+
+/// time to get from `pos` to `next` by restarting, if that's allowed
+fn restart_time(
+    problem: &Problem,
+    settings: &SolverSettings,
+    visited: &[bool],
+    pos: NodeIdx,
+    next: NodeIdx,
+) -> Option<Time> {
+    if pos == problem.start || !can_restart_after(problem, settings, visited, pos) {
+        return None;
+    }
+    let restart_time = problem.restart_time(pos)?;
+    Some(restart_time.saturating_add(edge(problem, problem.start, next)?))
+}
+
+struct LocalSearch<'a> {
+    problem: &'a Problem,
+    settings: &'a SolverSettings,
+    known: &'a mut KnownRoutes,
+    budget: usize,
+}
+
+impl LocalSearch<'_> {
+    fn run(&mut self) {
+        let Some(mut order) = self.nearest_neighbour() else {
+            return;
+        };
+        let Some(mut time) = self.evaluate(&order) else {
+            return;
+        };
+
+        loop {
+            let improved = self.two_opt(&mut order, &mut time)
+                | self.or_opt(&mut order, &mut time)
+                | self.skip_optional(&mut order, &mut time);
+            if !improved || self.budget == 0 {
+                break;
+            }
+        }
+    }
+
+    /// Greedily visits the place which is quickest to get to next, directly or by restarting.
+    /// Backtracks when the remaining places can't be reached anymore, up to [`CONSTRUCTION_BUDGET`] steps.
+    /// Returns the order of the places, without the start and finish.
+    fn nearest_neighbour(&self) -> Option<Vec<NodeIdx>> {
+        let problem = self.problem;
+        let places = problem.n.checked_sub(2)?;
+
+        let mut order = Vec::with_capacity(places);
+        let mut visited = vec![false; problem.n];
+        // places left to try after each prefix of the order, the quickest one last
+        let mut choices = vec![self.candidates(&visited, problem.start)];
+        let mut budget = CONSTRUCTION_BUDGET;
+
+        while let Some(candidates) = choices.last_mut() {
+            if order.len() == places {
+                return Some(order);
+            }
+
+            match candidates.pop() {
+                Some(next) => {
+                    if !self.everything_reachable(&visited, next) {
+                        continue;
+                    }
+                    budget = budget.checked_sub(1)?;
+
+                    visited[next] = true;
+                    order.push(next);
+                    choices.push(self.candidates(&visited, next));
+                }
+                None => {
+                    choices.pop();
+                    let node = order.pop()?;
+                    visited[node] = false;
+                }
+            }
+        }
+
+        None
+    }
+
+    /// unvisited places which can be reached from `pos`, sorted from slowest to quickest
+    fn candidates(&self, visited: &[bool], pos: NodeIdx) -> Vec<NodeIdx> {
+        let problem = self.problem;
+        let mut candidates: Vec<_> = (0..problem.n)
+            .filter(|&node| node != problem.start && node != problem.finish && !visited[node])
+            .filter(|&node| problem.can_visit(node, |before| visited[before]))
+            .filter_map(|node| Some((self.step_time(visited, pos, node)?, node)))
+            .collect();
+        candidates.sort_unstable_by(|a, b| b.cmp(a));
+        candidates.into_iter().map(|(_, node)| node).collect()
+    }
+
+    /// whether every unvisited node, including the finish, can still be reached after going to `next`
+    fn everything_reachable(&self, visited: &[bool], next: NodeIdx) -> bool {
+        let problem = self.problem;
+        let mut reached = visited.to_vec();
+        reached[next] = true;
+
+        let mut stack = vec![next];
+        if self.settings.max_restarts != Some(0) {
+            stack.push(problem.start);
+        }
+        while let Some(node) = stack.pop() {
+            for &target in &problem.nodes[node].targets {
+                if !reached[target] {
+                    reached[target] = true;
+                    stack.push(target);
+                }
+            }
+        }
+
+        (0..problem.n).all(|node| node == problem.start || reached[node])
+    }
+
+    /// quickest way to get from `pos` to `next`, ignoring the restart limit
+    fn step_time(&self, visited: &[bool], pos: NodeIdx, next: NodeIdx) -> Option<Time> {
+        let direct = edge(self.problem, pos, next);
+        let restart = restart_time(self.problem, self.settings, visited, pos, next);
+
+        match (direct, restart) {
+            (Some(direct), Some(restart)) => Some(direct.min(restart)),
+            (direct, restart) => direct.or(restart),
+        }
+    }
+
+    /// Places the restarts optimally for `order` and returns the time of the route, remembering it if it's interesting
+    fn evaluate(&mut self, order: &[NodeIdx]) -> Option<Time> {
+        self.budget = self.budget.saturating_sub(1);
+
+        let problem = self.problem;
+        let settings = self.settings;
+
+        let mut visited = vec![false; problem.n];
+        let mut time: Time = 0;
+        let mut required_restarts = 0;
+        // time saved by restarting instead of going directly, and the step index
+        let mut savings = Vec::new();
+        let mut restarts = vec![false; order.len() + 1];
+
+        let mut pos = problem.start;
+        for (i, &next) in order.iter().chain([&problem.finish]).enumerate() {
+            visited[pos] = true;
+            if !problem.can_visit(next, |before| visited[before]) {
+                return None;
+            }
+
+            let direct = edge(problem, pos, next);
+            let restart = restart_time(problem, settings, &visited, pos, next);
+
+            match (direct, restart) {
+                (Some(direct), Some(restart)) if restart < direct => {
+                    time = time.saturating_add(direct);
+                    savings.push((direct - restart, i));
+                }
+                (Some(direct), _) => time = time.saturating_add(direct),
+                (None, Some(restart)) => {
+                    time = time.saturating_add(restart);
+                    restarts[i] = true;
+                    required_restarts += 1;
+                }
+                (None, None) => return None,
+            }
+            pos = next;
+        }
+
+        let optional_restarts = match settings.max_restarts {
+            Some(max_restarts) => max_restarts.checked_sub(required_restarts)? as usize,
+            None => savings.len(),
+        };
+        if optional_restarts < savings.len() {
+            savings.sort_unstable_by(|a, b| b.cmp(a));
+        }
+        for &(saved, i) in savings.iter().take(optional_restarts) {
+            time -= saved;
+            restarts[i] = true;
+        }
+        time = time.saturating_add(problem.skip_penalty(|node| visited[node])?);
+
+        if self.known.is_interesting(time) {
+            let mut trail = Vec::with_capacity(order.len() + 2 + restarts.len());
+            trail.push(problem.start);
+            for (&next, &restart) in order.iter().chain([&problem.finish]).zip(&restarts) {
+                if restart {
+                    trail.push(problem.start);
+                }
+                trail.push(next);
+            }
+            self.known.insert(&trail, time);
+        }
+
+        Some(time)
+    }
+
+    /// reverses segments of the order while that makes the route faster
+    fn two_opt(&mut self, order: &mut [NodeIdx], time: &mut Time) -> bool {
+        let mut improved = false;
+        for i in 0..order.len() {
+            for j in i + 1..order.len() {
+                if self.budget == 0 {
+                    return improved;
+                }
+
+                order[i..=j].reverse();
+                match self.evaluate(order) {
+                    Some(new_time) if new_time < *time => {
+                        *time = new_time;
+                        improved = true;
+                    }
+                    _ => order[i..=j].reverse(),
+                }
+            }
+        }
+        improved
+    }
+
+    /// moves segments of up to three places to other positions while that makes the route faster
+    fn or_opt(&mut self, order: &mut [NodeIdx], time: &mut Time) -> bool {
+        let mut improved = false;
+        for len in 1..=3.min(order.len()) {
+            for i in 0..=order.len() - len {
+                for j in 0..=order.len() - len {
+                    if i == j {
+                        continue;
+                    }
+                    if self.budget == 0 {
+                        return improved;
+                    }
+
+                    move_segment(order, i, j, len);
+                    match self.evaluate(order) {
+                        Some(new_time) if new_time < *time => {
+                            *time = new_time;
+                            improved = true;
+                        }
+                        _ => move_segment(order, j, i, len),
+                    }
+                }
+            }
+        }
+        improved
+    }
+
+    /// leaves out optional places while that makes the route faster
+    fn skip_optional(&mut self, order: &mut Vec<NodeIdx>, time: &mut Time) -> bool {
+        let mut improved = false;
+        let mut i = 0;
+        while i < order.len() {
+            if self.budget == 0 {
+                return improved;
+            }
+            if !self.problem.is_optional(order[i]) {
+                i += 1;
+                continue;
+            }
+
+            let node = order.remove(i);
+            match self.evaluate(order) {
+                Some(new_time) if new_time < *time => {
+                    *time = new_time;
+                    improved = true;
+                }
+                _ => {
+                    order.insert(i, node);
+                    i += 1;
+                }
+            }
+        }
+        improved
+    }
+}
+
+/// moves `order[from..from + len]` so that it starts at index `to`
+fn move_segment(order: &mut [NodeIdx], from: usize, to: usize, len: usize) {
+    if from < to {
+        order[from..to + len].rotate_left(len);
+    } else {
+        order[to..from + len].rotate_right(len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::solver::{
+        solve_table, solve_table_parallel, Backend, SolverSettings, TopSolutions, WarmStart,
+    };
+    use crate::testing::{all_routes, random_table, settings_variants, Rng};
+
+    #[test]
+    fn keeps_the_top_solutions_exact() {
+        let mut rng = Rng::new(7);
+        for case in 0..40 {
+            let n = 4 + case % 5;
+            let table = random_table(&mut rng, n, 20);
+            for settings in settings_variants(n) {
+                let routes = all_routes(&table, &settings);
+                let top_times = |warm_start: WarmStart| {
+                    let settings = SolverSettings {
+                        warm_start,
+                        ..settings.clone()
+                    };
+                    let mut top = TopSolutions::new(3);
+                    solve_table(&table, &settings, top.emit()).unwrap();
+                    top.into_solutions()
+                        .into_iter()
+                        .map(|(_, time)| time)
+                        .collect::<Vec<_>>()
+                };
+                let expected: Vec<_> = routes.iter().take(3).map(|&(_, time)| time).collect();
+
+                let heuristic = WarmStart {
+                    heuristic: true,
+                    solutions: 3,
+                    ..Default::default()
+                };
+                assert_eq!(
+                    top_times(heuristic),
+                    expected,
+                    "{table:?} with {settings:?}"
+                );
+                // slow known routes, more than the collector keeps
+                let known = WarmStart {
+                    routes: routes
+                        .iter()
+                        .rev()
+                        .take(10)
+                        .map(|(route, _)| route.clone())
+                        .collect(),
+                    solutions: 10,
+                    ..Default::default()
+                };
+                assert_eq!(top_times(known), expected, "{table:?} with {settings:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn known_time_bounds_the_top_solutions() {
+        let mut rng = Rng::new(8);
+        for case in 0..40 {
+            let n = 4 + case % 5;
+            let table = random_table(&mut rng, n, 20);
+            for settings in settings_variants(n) {
+                let routes = all_routes(&table, &settings);
+                let Some(&(_, known_time)) = routes.get(1) else {
+                    continue;
+                };
+                let expected: Vec<_> = routes
+                    .iter()
+                    .filter(|&&(_, time)| time <= known_time)
+                    .take(5)
+                    .map(|&(_, time)| time)
+                    .collect();
+
+                for backend in [Backend::BranchAndBound, Backend::HeldKarp] {
+                    let settings = SolverSettings {
+                        backend,
+                        warm_start: WarmStart {
+                            time: Some(known_time),
+                            solutions: 5,
+                            ..Default::default()
+                        },
+                        ..settings.clone()
+                    };
+                    for threads in [1, 3] {
+                        let mut top = TopSolutions::new(5);
+                        let mut emitted = 0;
+                        let stats = {
+                            let mut emit = top.emit();
+                            solve_table_parallel(&table, &settings, threads, |route, time| {
+                                emitted += 1;
+                                emit(route, time)
+                            })
+                            .unwrap()
+                        };
+                        // routes slower than the known time are neither emitted nor counted
+                        assert_eq!(stats.solutions_found, emitted);
+                        let times: Vec<_> = top
+                            .into_solutions()
+                            .into_iter()
+                            .map(|(_, time)| time)
+                            .collect();
+                        assert_eq!(times, expected, "{table:?} with {settings:?}");
+                    }
+                }
+            }
+        }
+    }
+}
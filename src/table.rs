@@ -0,0 +1,982 @@
+//! Table formats, see [`parse`].
+//!
+//! The original format has one row per map like `[190,0,257,60000]`, where the first column holds the restart times
+//! and 60000 marks missing connections.
+//!
+//! The second format names the maps and can hold more information about them:
+//!
+//! ```text
+//! # comments go until the end of the line
+//! map    completion  category  restart  1-A  1-B
+//! start  -           -         0        169  -     # the first map is the start
+//! 1-A    1200        a-side    190      0    257
+//! 1-B    1350        b-side    190      330  0
+//! ```
+//!
+//! The header starts with `map` and ends with `restart` and the names of every map except the first one, in the order
+//! of the rows. `completion` and `category` columns are optional. The restart column is the first column of the
+//! original format, and `-` marks missing connections. The completion time of a map is added to every connection
+//! into it, so the cells only need the time to get there.
+//!
+//! Tables from spreadsheets can be read and written as CSV or TSV with [`parse_delimited`] and
+//! [`TableInfo::write_delimited`].
+//!
+//! Sparse tables can list one connection per line instead, where restarts are connections into map 0:
+//!
+//! ```text
+//! maps 23             # optional, if the last map has no connections
+//! 0 -> 1 : 169        # source file
+//! 1 -> 0 : 190
+//! ```
+
+use crate::solver::{collect_files, FileInfo};
+use crate::MISSING;
+use std::fmt::Write;
+use std::ops::Range;
+
+mod error;
+pub use error::{ParseError, ParseErrorKind, Span};
+
+/// Connection times of a table, with what the table says about its maps
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableInfo {
+    /// time from each map to each other one, restarts in the first column
+    pub times: Vec<Vec<u32>>,
+    /// names of the maps, if the table has them
+    pub names: Option<Vec<String>>,
+    /// time to complete each map, already added to the connections into it
+    pub completion_times: Option<Vec<u32>>,
+    pub categories: Option<Vec<Option<String>>>,
+}
+
+impl TableInfo {
+    /// name of `map`, or its number if the table has no name for it
+    pub fn map_name(&self, map: usize) -> String {
+        match self.names.as_ref().and_then(|names| names.get(map)) {
+            Some(name) => name.clone(),
+            None => map.to_string(),
+        }
+    }
+
+    /// `maps` like `[start, 1-A, 1-B]`, or like `[0, 1, 2]` if the table has no names
+    pub fn format_maps(&self, maps: &[usize]) -> String {
+        let maps: Vec<String> = maps.iter().map(|&map| self.map_name(map)).collect();
+        format!("[{}]", maps.join(", "))
+    }
+
+    /// Table of `maps` maps with the connections of `files`, and no names.
+    pub fn from_files(maps: usize, files: &[FileInfo]) -> TableInfo {
+        let mut times = vec![vec![MISSING; maps]; maps];
+        for (map, row) in times.iter_mut().enumerate() {
+            row[map] = 0;
+        }
+        for file in files {
+            times[file.start][file.end] = file.time;
+        }
+        TableInfo {
+            times,
+            ..Default::default()
+        }
+    }
+
+    /// Writes the table with one connection per line, see [`parse_edges`].
+    pub fn write_edges(&self) -> String {
+        let mut table = format!("maps {}\n", self.times.len());
+        for file in collect_files(&self.times) {
+            let _ = writeln!(table, "{} -> {} : {}", file.start, file.end, file.time);
+        }
+        table
+    }
+
+    /// Writes the table in the original format, without names.
+    pub fn write_matrix(&self) -> String {
+        let mut table = String::new();
+        for row in &self.times {
+            let row: Vec<String> = row.iter().map(u32::to_string).collect();
+            let _ = writeln!(table, "[{}]", row.join(","));
+        }
+        table
+    }
+
+    /// Writes the table in the format with names, using the numbers of maps without names.
+    ///
+    /// Whitespace and `#` in names are replaced by `_`, which the format can't contain.
+    /// Maps without a completion time get `-`, and comments of a parsed table aren't kept.
+    pub fn write_named(&self) -> String {
+        let n = self.times.len();
+        let names: Vec<String> = (0..n)
+            .map(|map| {
+                self.map_name(map)
+                    .replace(|c: char| c.is_whitespace() || c == '#', "_")
+            })
+            .collect();
+        let completion_time = |map: usize| {
+            self.completion_times
+                .as_ref()
+                .map_or(0, |completion_times| completion_times[map])
+        };
+
+        let mut rows = Vec::with_capacity(n + 1);
+        let mut header = vec!["map".to_owned()];
+        if self.completion_times.is_some() {
+            header.push("completion".to_owned());
+        }
+        if self.categories.is_some() {
+            header.push("category".to_owned());
+        }
+        header.push("restart".to_owned());
+        header.extend(names[1..].iter().cloned());
+        rows.push(header);
+
+        for (from, times) in self.times.iter().enumerate() {
+            let mut row = vec![names[from].clone()];
+            if self.completion_times.is_some() {
+                row.push(match completion_time(from) {
+                    0 => "-".to_owned(),
+                    time => time.to_string(),
+                });
+            }
+            if let Some(categories) = &self.categories {
+                row.push(categories[from].clone().unwrap_or_else(|| "-".to_owned()));
+            }
+            row.extend(times.iter().enumerate().map(|(to, &time)| {
+                if time >= MISSING {
+                    "-".to_owned()
+                } else if to > 0 && to != from {
+                    (time - completion_time(to)).to_string()
+                } else {
+                    time.to_string()
+                }
+            }));
+            rows.push(row);
+        }
+
+        let mut widths = vec![0; rows[0].len()];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+        let mut table = String::new();
+        for row in &rows {
+            let line: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:width$}"))
+                .collect();
+            let _ = writeln!(table, "{}", line.join("  ").trim_end());
+        }
+        table
+    }
+
+    /// Writes the table as CSV or TSV, see [`DelimitedOptions`].
+    pub fn write_delimited(&self, options: &DelimitedOptions) -> String {
+        let quote = |cell: &str| {
+            if cell.contains([options.delimiter, '"']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.to_owned()
+            }
+        };
+        let delimiter = options.delimiter.to_string();
+        let missing = options
+            .missing
+            .first()
+            .cloned()
+            .unwrap_or_else(|| MISSING.to_string());
+
+        let mut table = String::new();
+        if options.header {
+            let mut header = Vec::new();
+            if options.labels {
+                header.push(String::new());
+            }
+            header.extend((0..self.times.len()).map(|map| quote(&self.map_name(map))));
+            let _ = writeln!(table, "{}", header.join(&delimiter));
+        }
+        for (map, times) in self.times.iter().enumerate() {
+            let mut row = Vec::new();
+            if options.labels {
+                row.push(quote(&self.map_name(map)));
+            }
+            row.extend(times.iter().map(|&time| {
+                if time >= MISSING {
+                    quote(&missing)
+                } else {
+                    time.to_string()
+                }
+            }));
+            let _ = writeln!(table, "{}", row.join(&delimiter));
+        }
+        table
+    }
+}
+
+/// How a table is laid out as delimited text like CSV
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DelimitedOptions {
+    /// `,` for CSV and a tab for TSV
+    pub delimiter: char,
+    /// whether the first row has the names of the maps
+    pub header: bool,
+    /// whether the first column has the names of the maps
+    pub labels: bool,
+    /// cells which mean missing connections, the first one is used when writing
+    pub missing: Vec<String>,
+}
+
+impl DelimitedOptions {
+    pub fn csv() -> DelimitedOptions {
+        DelimitedOptions::default()
+    }
+
+    pub fn tsv() -> DelimitedOptions {
+        DelimitedOptions {
+            delimiter: '\t',
+            ..DelimitedOptions::default()
+        }
+    }
+}
+
+impl Default for DelimitedOptions {
+    fn default() -> Self {
+        DelimitedOptions {
+            delimiter: ',',
+            header: false,
+            labels: false,
+            missing: vec![String::new(), "-".to_owned()],
+        }
+    }
+}
+
+/// Parses a table in the original format, with names or with one connection per line, depending on its first line.
+pub fn parse(table: &str) -> Result<TableInfo, ParseError> {
+    match content_lines(table).next() {
+        Some((_, _, content)) if content.text.starts_with('[') => parse_matrix(table),
+        Some((_, _, content)) if content.text.split_whitespace().next() == Some("map") => {
+            parse_named(table)
+        }
+        Some((_, _, content))
+            if content.text.contains("->")
+                || content.text.split_whitespace().next() == Some("maps") =>
+        {
+            let (maps, files) = parse_edges(table)?;
+            Ok(TableInfo::from_files(maps, &files))
+        }
+        Some((line_number, line, content)) => Err(ParseError::at(
+            ParseErrorKind::UnknownFormat,
+            line_number,
+            line,
+            content.range(),
+        )),
+        None => Err(ParseError::new(ParseErrorKind::Empty)),
+    }
+}
+
+/// lines with their number and their content without comments, leaving out the ones without content
+fn content_lines(table: &str) -> impl Iterator<Item = (usize, &str, Piece<'_>)> {
+    table
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line, strip_comment(line)))
+        .filter(|(_, _, content)| !content.text.is_empty())
+}
+
+fn strip_comment(line: &str) -> Piece<'_> {
+    let line = Piece::line(line);
+    line.split_once("#").map_or(line, |(line, _)| line).trim()
+}
+
+/// Part of a line of a table, with where in the line it is, so errors can point at it
+#[derive(Debug, Clone, Copy)]
+struct Piece<'a> {
+    text: &'a str,
+    /// byte of the line `text` starts at
+    start: usize,
+}
+
+impl<'a> Piece<'a> {
+    fn line(line: &'a str) -> Piece<'a> {
+        Piece {
+            text: line,
+            start: 0,
+        }
+    }
+
+    /// bytes of the line this is
+    fn range(self) -> Range<usize> {
+        self.start..self.start + self.text.len()
+    }
+
+    /// the bytes `range` of this piece
+    fn slice(self, range: Range<usize>) -> Piece<'a> {
+        Piece {
+            text: &self.text[range.clone()],
+            start: self.start + range.start,
+        }
+    }
+
+    fn trim(self) -> Piece<'a> {
+        let start = self.text.len() - self.text.trim_start().len();
+        let end = self.text.trim_end().len().max(start);
+        self.slice(start..end)
+    }
+
+    fn strip_prefix(self, prefix: &str) -> Option<Piece<'a>> {
+        self.strip_around(prefix, "")
+    }
+
+    fn strip_around(self, prefix: &str, suffix: &str) -> Option<Piece<'a>> {
+        let inner = self.text.strip_prefix(prefix)?.strip_suffix(suffix)?;
+        Some(self.slice(prefix.len()..prefix.len() + inner.len()))
+    }
+
+    fn split_once(self, delimiter: &str) -> Option<(Piece<'a>, Piece<'a>)> {
+        let i = self.text.find(delimiter)?;
+        Some((
+            self.slice(0..i),
+            self.slice(i + delimiter.len()..self.text.len()),
+        ))
+    }
+
+    fn split(self, delimiter: char) -> impl Iterator<Item = Piece<'a>> {
+        let mut start = 0;
+        self.text.split(delimiter).map(move |part| {
+            let piece = self.slice(start..start + part.len());
+            start += part.len() + delimiter.len_utf8();
+            piece
+        })
+    }
+
+    fn split_whitespace(self) -> impl Iterator<Item = Piece<'a>> {
+        let mut rest = self;
+        std::iter::from_fn(move || {
+            rest = rest.slice(rest.text.len() - rest.text.trim_start().len()..rest.text.len());
+            if rest.text.is_empty() {
+                return None;
+            }
+            let end = rest
+                .text
+                .find(char::is_whitespace)
+                .unwrap_or(rest.text.len());
+            let word = rest.slice(0..end);
+            rest = rest.slice(end..rest.text.len());
+            Some(word)
+        })
+    }
+}
+
+fn parse_matrix(table: &str) -> Result<TableInfo, ParseError> {
+    let mut times: Vec<Vec<u32>> = Vec::new();
+    let mut first_row = None;
+    for (line_number, line, content) in content_lines(table) {
+        first_row.get_or_insert((line_number, line, content));
+        let error = |kind, piece: Piece| ParseError::at(kind, line_number, line, piece.range());
+        let cells = content
+            .strip_around("[", "]")
+            .ok_or_else(|| error(ParseErrorKind::NotAnArray, content))?;
+        let row = cells
+            .split(',')
+            .map(|cell| {
+                let trimmed = cell.trim();
+                if trimmed.text.is_empty() {
+                    return Err(error(ParseErrorKind::EmptyCell, cell));
+                }
+                trimmed
+                    .text
+                    .parse::<u32>()
+                    .map_err(|e| error(ParseErrorKind::InvalidNumber(e), trimmed))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(expected) = times.first().map(Vec::len) {
+            if row.len() != expected {
+                let kind = ParseErrorKind::RowLength {
+                    expected,
+                    found: row.len(),
+                };
+                return Err(error(kind, content));
+            }
+        }
+        times.push(row);
+    }
+
+    let Some((line_number, line, content)) = first_row else {
+        return Err(ParseError::new(ParseErrorKind::Empty));
+    };
+    if times[0].len() != times.len() {
+        let kind = ParseErrorKind::NotSquare {
+            rows: times.len(),
+            columns: times[0].len(),
+        };
+        return Err(ParseError::at(kind, line_number, line, content.range()));
+    }
+    Ok(TableInfo {
+        times,
+        ..Default::default()
+    })
+}
+
+fn parse_named(table: &str) -> Result<TableInfo, ParseError> {
+    let mut rows = content_lines(table).map(|(line_number, line, content)| {
+        (
+            line_number,
+            line,
+            content,
+            content.split_whitespace().collect::<Vec<_>>(),
+        )
+    });
+    let (header_line_number, header_line, header_content, header) = rows
+        .next()
+        .ok_or_else(|| ParseError::new(ParseErrorKind::Empty))?;
+    let header_error =
+        |kind, piece: Piece| ParseError::at(kind, header_line_number, header_line, piece.range());
+    let rows: Vec<_> = rows.collect();
+    if rows.is_empty() {
+        return Err(header_error(ParseErrorKind::NoMaps, header_content));
+    }
+
+    let restart_column = header
+        .iter()
+        .position(|column| column.text == "restart")
+        .ok_or_else(|| header_error(ParseErrorKind::NoRestartColumn, header_content))?;
+    let column = |name: &str| {
+        header[1..restart_column]
+            .iter()
+            .position(|column| column.text == name)
+    };
+    let completion_column = column("completion").map(|i| i + 1);
+    let category_column = column("category").map(|i| i + 1);
+    if let Some(&unknown) = header[1..restart_column]
+        .iter()
+        .find(|column| column.text != "completion" && column.text != "category")
+    {
+        return Err(header_error(ParseErrorKind::UnknownColumn, unknown));
+    }
+
+    let n = rows.len();
+    let columns = restart_column + n;
+    if header.len() != columns {
+        let kind = ParseErrorKind::HeaderLength {
+            expected: n - 1,
+            found: header.len() - restart_column - 1,
+        };
+        return Err(header_error(kind, header_content));
+    }
+
+    let mut names: Vec<String> = Vec::with_capacity(n);
+    let mut times = Vec::with_capacity(n);
+    let mut completion_times = vec![0; n];
+    let mut categories = vec![None; n];
+    for (i, (line_number, line, content, row)) in rows.iter().enumerate() {
+        let error = |kind, piece: Piece| ParseError::at(kind, *line_number, line, piece.range());
+        let name = row[0];
+        if row.len() != columns {
+            let kind = ParseErrorKind::RowLength {
+                expected: columns,
+                found: row.len(),
+            };
+            return Err(error(kind, *content));
+        }
+        if names.iter().any(|other| other == name.text) {
+            return Err(error(ParseErrorKind::DuplicateMap, name));
+        }
+        if i > 0 && header[restart_column + i].text != name.text {
+            let kind = ParseErrorKind::NameMismatch {
+                expected: header[restart_column + i].text.to_owned(),
+            };
+            return Err(error(kind, name));
+        }
+
+        let parse_time = |cell: Piece| -> Result<u32, ParseError> {
+            if cell.text == "-" {
+                return Ok(MISSING);
+            }
+            cell.text
+                .parse()
+                .map_err(|e| error(ParseErrorKind::InvalidNumber(e), cell))
+        };
+        if let Some(column) = completion_column {
+            completion_times[i] = match row[column].text {
+                "-" => 0,
+                _ => parse_time(row[column])?,
+            };
+        }
+        if let Some(column) = category_column {
+            categories[i] = Some(row[column].text)
+                .filter(|&category| category != "-")
+                .map(str::to_owned);
+        }
+        names.push(name.text.to_owned());
+        times.push(
+            row[restart_column..]
+                .iter()
+                .map(|&cell| parse_time(cell))
+                .collect::<Result<Vec<_>, _>>()?,
+        );
+    }
+
+    for (from, row) in times.iter_mut().enumerate() {
+        for (to, time) in row.iter_mut().enumerate().skip(1) {
+            if to != from && *time < MISSING {
+                *time += completion_times[to];
+                if *time >= MISSING {
+                    let (line_number, line, _, cells) = &rows[from];
+                    let kind = ParseErrorKind::TooLong { time: *time };
+                    let range = cells[restart_column + to].range();
+                    return Err(ParseError::at(kind, *line_number, line, range));
+                }
+            }
+        }
+    }
+
+    Ok(TableInfo {
+        times,
+        names: Some(names),
+        completion_times: completion_column.map(|_| completion_times),
+        categories: category_column.map(|_| categories),
+    })
+}
+
+/// Parses a table exported as CSV or TSV, which has the same columns as the original format.
+///
+/// Cells can be quoted like `"1-A, upper"`, and numbers of at least 60000 are missing connections as well.
+pub fn parse_delimited(table: &str, options: &DelimitedOptions) -> Result<TableInfo, ParseError> {
+    let mut rows = table
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| (i + 1, line, split_delimited(line, options.delimiter)));
+    let labels_column = options.labels as usize;
+
+    let header = match options.header {
+        true => {
+            let (line_number, line, cells) = rows
+                .next()
+                .ok_or_else(|| ParseError::new(ParseErrorKind::Empty))?;
+            Some((
+                line_number,
+                line,
+                cells[labels_column.min(cells.len())..].to_vec(),
+            ))
+        }
+        false => None,
+    };
+
+    let mut labels = Vec::new();
+    let mut times = Vec::new();
+    let mut first_row = None;
+    for (line_number, line, mut cells) in rows {
+        first_row.get_or_insert((line_number, line));
+        let error = |kind, range| ParseError::at(kind, line_number, line, range);
+        if options.labels {
+            labels.push((line_number, line, cells.remove(0)));
+        }
+        if cells.is_empty() {
+            return Err(error(ParseErrorKind::EmptyCell, line.len()..line.len()));
+        }
+        let row = cells
+            .iter()
+            .map(|(cell, range)| {
+                if options.missing.contains(cell) {
+                    return Ok(MISSING);
+                }
+                cell.parse::<u32>()
+                    .map_err(|e| error(ParseErrorKind::InvalidNumber(e), range.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(expected) = times.first().map(Vec::len) {
+            if row.len() != expected {
+                let kind = ParseErrorKind::RowLength {
+                    expected,
+                    found: row.len(),
+                };
+                return Err(error(kind, 0..line.len()));
+            }
+        }
+        times.push(row);
+    }
+    let Some((line_number, line)) = first_row else {
+        return Err(ParseError::new(ParseErrorKind::Empty));
+    };
+
+    let n = times.len();
+    if times[0].len() != n {
+        let kind = ParseErrorKind::NotSquare {
+            rows: n,
+            columns: times[0].len(),
+        };
+        return Err(ParseError::at(kind, line_number, line, 0..line.len()));
+    }
+    if let Some((line_number, line, header)) = &header {
+        if header.len() != n {
+            let kind = ParseErrorKind::HeaderLength {
+                expected: n,
+                found: header.len(),
+            };
+            return Err(ParseError::at(kind, *line_number, line, 0..line.len()));
+        }
+        if let Some(((line_number, line, (_, range)), (name, _))) = labels
+            .iter()
+            .zip(header)
+            .find(|((_, _, (label, _)), (name, _))| label != name)
+        {
+            let kind = ParseErrorKind::NameMismatch {
+                expected: name.clone(),
+            };
+            return Err(ParseError::at(kind, *line_number, line, range.clone()));
+        }
+    }
+
+    // where each name is, to point at the second one if it appears twice
+    let names: Option<Vec<(usize, &str, Cell)>> = match header {
+        Some((line_number, line, header)) => Some(
+            header
+                .into_iter()
+                .map(|cell| (line_number, line, cell))
+                .collect(),
+        ),
+        None => options.labels.then_some(labels),
+    };
+    if let Some(names) = &names {
+        for (i, (line_number, line, (name, range))) in names.iter().enumerate() {
+            if names[..i].iter().any(|(_, _, (other, _))| other == name) {
+                let kind = ParseErrorKind::DuplicateMap;
+                return Err(ParseError::at(kind, *line_number, line, range.clone()));
+            }
+        }
+    }
+
+    Ok(TableInfo {
+        times,
+        names: names.map(|names| names.into_iter().map(|(_, _, (name, _))| name).collect()),
+        ..Default::default()
+    })
+}
+
+/// cell of a CSV or TSV line, with the bytes of the line it is in
+type Cell = (String, Range<usize>);
+
+/// splits a line at `delimiter` outside of quotes, into the trimmed cells
+fn split_delimited(line: &str, delimiter: char) -> Vec<Cell> {
+    let trimmed = |range: Range<usize>| {
+        let raw = &line[range.clone()];
+        let start = range.start + raw.len() - raw.trim_start().len();
+        start..start.max(range.end - (raw.len() - raw.trim_end().len()))
+    };
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut cell_start = 0;
+    let mut quoted = false;
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek().map(|&(_, c)| c) == Some('"') => {
+                cell.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => {
+                cells.push((std::mem::take(&mut cell), trimmed(cell_start..i)));
+                cell_start = i + c.len_utf8();
+            }
+            c => cell.push(c),
+        }
+    }
+    cells.push((cell, trimmed(cell_start..line.len())));
+    cells
+        .into_iter()
+        .map(|(cell, range)| (cell.trim().to_owned(), range))
+        .collect()
+}
+
+/// Parses a table with one connection per line like `3 -> 7 : 412`, into the number of maps and its connections,
+/// ordered by start and end like the ones taken from a matrix.
+///
+/// The number of maps is one more than the highest map, unless a `maps <n>` line gives it.
+/// Connections which are given more than once are reported with the line they are first given in.
+pub fn parse_edges(table: &str) -> Result<(usize, Vec<FileInfo>), ParseError> {
+    let mut maps = None;
+    let mut edges: Vec<Edge> = Vec::new();
+    for (line_number, line, content) in content_lines(table) {
+        let error = |kind, piece: Piece| ParseError::at(kind, line_number, line, piece.range());
+        if let Some(n) = content.strip_prefix("maps") {
+            if maps.is_some() {
+                return Err(error(ParseErrorKind::MapCountTwice, content));
+            }
+            let n = n.trim();
+            maps = Some(
+                n.text
+                    .parse::<usize>()
+                    .map_err(|e| error(ParseErrorKind::InvalidMapCount(e), n))?,
+            );
+            continue;
+        }
+
+        let (start, rest) = content
+            .split_once("->")
+            .ok_or_else(|| error(ParseErrorKind::InvalidConnection, content))?;
+        let (end, time) = rest
+            .split_once(":")
+            .ok_or_else(|| error(ParseErrorKind::MissingTime, content))?;
+        let (start_text, end_text, time_text) = (start.trim(), end.trim(), time.trim());
+        let parse_map = |map: Piece| {
+            map.text
+                .parse::<usize>()
+                .map_err(|e| error(ParseErrorKind::InvalidMap(e), map))
+        };
+        let (start, end) = (parse_map(start_text)?, parse_map(end_text)?);
+        let time = time_text
+            .text
+            .parse::<u32>()
+            .map_err(|e| error(ParseErrorKind::InvalidNumber(e), time_text))?;
+        if start == end {
+            return Err(error(ParseErrorKind::SelfConnection, end_text));
+        }
+        if time >= MISSING {
+            return Err(error(ParseErrorKind::MissingConnection { time }, time_text));
+        }
+        edges.push(Edge {
+            file: FileInfo { start, end, time },
+            line_number,
+            line,
+            content,
+            highest: if start > end { start_text } else { end_text },
+        });
+    }
+
+    let maps = match maps {
+        Some(maps) => {
+            if let Some(edge) = edges
+                .iter()
+                .find(|edge| edge.file.start.max(edge.file.end) >= maps)
+            {
+                return Err(edge.error(ParseErrorKind::OutsideTable { maps }, edge.highest));
+            }
+            maps
+        }
+        None => edges
+            .iter()
+            .map(|edge| edge.file.start.max(edge.file.end) + 1)
+            .max()
+            .unwrap_or(0),
+    };
+    if maps == 0 {
+        return Err(ParseError::new(ParseErrorKind::Empty));
+    }
+
+    edges.sort_by_key(|edge| (edge.file.start, edge.file.end, edge.line_number));
+    let mut repeated = edges.windows(2).filter(|pair| {
+        (pair[0].file.start, pair[0].file.end) == (pair[1].file.start, pair[1].file.end)
+    });
+    if let Some([first, edge]) = repeated.next() {
+        let kind = ParseErrorKind::Repeated {
+            first_line: first.line_number,
+            first_time: first.file.time,
+            time: edge.file.time,
+            more: repeated.count(),
+        };
+        return Err(edge.error(kind, edge.content));
+    }
+
+    Ok((maps, edges.into_iter().map(|edge| edge.file).collect()))
+}
+
+/// connection of an edge list, with where it is for errors
+struct Edge<'a> {
+    file: FileInfo,
+    line_number: usize,
+    line: &'a str,
+    content: Piece<'a>,
+    /// the higher one of the maps
+    highest: Piece<'a>,
+}
+
+impl Edge<'_> {
+    fn error(&self, kind: ParseErrorKind, piece: Piece) -> ParseError {
+        ParseError::at(kind, self.line_number, self.line, piece.range())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{random_table, Rng};
+
+    #[test]
+    fn rejects_tables_which_arent_square() {
+        let error = parse("[0,1,2,3]\n[1,0,2,3]\n[1,2,0,3]\n").unwrap_err();
+        let kind = ParseErrorKind::NotSquare {
+            rows: 3,
+            columns: 4,
+        };
+        assert_eq!(error.kind, kind);
+        assert_eq!(error.text, "[0,1,2,3]");
+
+        let error = parse_delimited("0,1\n1,0\n1,2\n", &DelimitedOptions::csv()).unwrap_err();
+        let kind = ParseErrorKind::NotSquare {
+            rows: 3,
+            columns: 2,
+        };
+        assert_eq!(error.kind, kind);
+        assert_eq!(error.span.map(|span| span.line), Some(1));
+    }
+
+    #[test]
+    fn detects_the_format_from_the_first_line() {
+        let times = vec![vec![0, 169, MISSING], vec![190, 0, 257], vec![0, 330, 0]];
+        let matrix = "# comment\n[0,169,60000]\n[190,0,257]\n[0,330,0]\n";
+        assert_eq!(parse(matrix).unwrap().times, times);
+
+        let named = "
+            # comment
+            map    restart  1-A  1-B
+            start  0        169  -
+            1-A    190      0    257
+            1-B    0        330  0
+        ";
+        let table_info = parse(named).unwrap();
+        assert_eq!(table_info.times, times);
+        assert_eq!(table_info.names.unwrap(), ["start", "1-A", "1-B"]);
+
+        let edges = "0 -> 1 : 169\n1 -> 0 : 190\n1 -> 2 : 257\n2 -> 1 : 330\n2 -> 0 : 0\n";
+        assert_eq!(parse(edges).unwrap().times, times);
+        let edges = "maps 4\n0 -> 1 : 169\n";
+        assert_eq!(parse(edges).unwrap().times.len(), 4);
+
+        let csv = "0,169,-\n190,0,257\n0,330,0\n";
+        let tsv = csv.replace(',', "\t");
+        assert_eq!(
+            parse_delimited(csv, &DelimitedOptions::csv())
+                .unwrap()
+                .times,
+            times
+        );
+        assert_eq!(
+            parse_delimited(&tsv, &DelimitedOptions::tsv())
+                .unwrap()
+                .times,
+            times
+        );
+
+        assert_eq!(
+            parse("0,169,-\n").unwrap_err().kind,
+            ParseErrorKind::UnknownFormat
+        );
+        assert_eq!(
+            parse("# only a comment\n").unwrap_err().kind,
+            ParseErrorKind::Empty
+        );
+    }
+
+    #[test]
+    fn reports_the_malformed_line_of_each_format() {
+        let error = parse("[0,169]\n[190,x]\n").unwrap_err();
+        assert!(matches!(error.kind, ParseErrorKind::InvalidNumber(_)));
+        assert_eq!((error.span.unwrap().line, error.text.as_str()), (2, "x"));
+
+        let error = parse("[0,169]\n[190,0,257]\n").unwrap_err();
+        let kind = ParseErrorKind::RowLength {
+            expected: 2,
+            found: 3,
+        };
+        assert_eq!((error.kind, error.span.unwrap().line), (kind, 2));
+
+        let error = parse("map restart 1-A\nstart 0 169\n1-B 190 0\n").unwrap_err();
+        let kind = ParseErrorKind::NameMismatch {
+            expected: "1-A".to_owned(),
+        };
+        assert_eq!((error.kind, error.text.as_str()), (kind, "1-B"));
+
+        let error = parse("map restart 1-A\nstart 0 169\n1-A 190\n").unwrap_err();
+        let kind = ParseErrorKind::RowLength {
+            expected: 3,
+            found: 2,
+        };
+        assert_eq!((error.kind, error.span.unwrap().line), (kind, 3));
+
+        let options = DelimitedOptions {
+            header: true,
+            labels: true,
+            ..DelimitedOptions::csv()
+        };
+        let error = parse_delimited(
+            ",start,\"1-A, upper\"\nstart,0,169\n\"1-A, upper\",190,0 x\n",
+            &options,
+        )
+        .unwrap_err();
+        assert!(matches!(error.kind, ParseErrorKind::InvalidNumber(_)));
+        assert_eq!((error.span.unwrap().line, error.text.as_str()), (3, "0 x"));
+
+        let error = parse("0 -> 1 : 169\n1 -> 0 190\n").unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::MissingTime);
+        assert_eq!(error.span.unwrap().line, 2);
+
+        let error = parse("0 -> 1 : 169\n1 -> 0 : 190\n0 -> 1 : 170\n").unwrap_err();
+        let kind = ParseErrorKind::Repeated {
+            first_line: 1,
+            first_time: 169,
+            time: 170,
+            more: 0,
+        };
+        assert_eq!((error.kind, error.span.unwrap().line), (kind, 3));
+    }
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let named = "
+            map    completion  category  restart  1-A  1-B   2-A
+            start  -           -         0        169  -     -    # the start
+            1-A    1200        a-side    190      0    257   -
+            1-B    1350        b-side    190      330  0     400
+            2-A    -           -         0        0    0     0
+        ";
+        let table_info = parse(named).unwrap();
+        let written = table_info.write_named();
+        assert!(written.lines().nth(1).unwrap().starts_with("start  -"));
+        assert_eq!(parse(&written).unwrap(), table_info);
+
+        let options = DelimitedOptions {
+            header: true,
+            labels: true,
+            ..DelimitedOptions::tsv()
+        };
+        let delimited = parse_delimited(&table_info.write_delimited(&options), &options).unwrap();
+        assert_eq!(delimited.times, table_info.times);
+        assert_eq!(delimited.names, table_info.names);
+
+        let mut rng = Rng::new(23);
+        for case in 0..20 {
+            let times = random_table(&mut rng, 2 + case % 5, 40);
+            let table_info = TableInfo {
+                times: times.clone(),
+                ..Default::default()
+            };
+            assert_eq!(parse(&table_info.write_matrix()).unwrap(), table_info);
+            assert_eq!(parse(&table_info.write_edges()).unwrap().times, times);
+            assert_eq!(parse(&table_info.write_named()).unwrap().times, times);
+            for options in [DelimitedOptions::csv(), DelimitedOptions::tsv()] {
+                let written = table_info.write_delimited(&options);
+                assert_eq!(parse_delimited(&written, &options).unwrap(), table_info);
+            }
+        }
+    }
+
+    #[test]
+    fn pieces_know_where_they_are_in_their_line() {
+        let line = " [1-Ä,\t 2 ] 3\u{3000}-> x : 4 # comment";
+        let content = strip_comment(line);
+        assert_eq!(content.text, "[1-Ä,\t 2 ] 3\u{3000}-> x : 4");
+        let (start, rest) = content.split_once("->").unwrap();
+        let (end, time) = rest.split_once(":").unwrap();
+        let cells = start.trim().strip_around("[", "] 3").unwrap();
+        let pieces = [content, start, end.trim(), time.trim()]
+            .into_iter()
+            .chain(cells.split(','))
+            .chain(start.split_whitespace());
+        for piece in pieces {
+            assert_eq!(&line[piece.range()], piece.text);
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use std::fmt;
+use std::num::ParseIntError;
+use std::ops::Range;
+
+/// Where in a table something is, counted in characters from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub len: usize,
+}
+
+impl Span {
+    /// span of the bytes `range` of `line`, which is line `line_number` of the table
+    pub(crate) fn new(line_number: usize, line: &str, range: Range<usize>) -> Span {
+        Span {
+            line: line_number,
+            column: line[..range.start].chars().count() + 1,
+            len: line[range].chars().count(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    Empty,
+    /// the first line doesn't look like any of the formats
+    UnknownFormat,
+    NotAnArray,
+    EmptyCell,
+    InvalidNumber(ParseIntError),
+    RowLength {
+        expected: usize,
+        found: usize,
+    },
+    /// the rows have `columns` cells, but there are `rows` of them
+    NotSquare {
+        rows: usize,
+        columns: usize,
+    },
+    /// the header names `found` maps, but the rows have `expected`
+    HeaderLength {
+        expected: usize,
+        found: usize,
+    },
+    NoMaps,
+    NoRestartColumn,
+    UnknownColumn,
+    DuplicateMap,
+    /// the name of a row doesn't match the `expected` one from the header
+    NameMismatch {
+        expected: String,
+    },
+    /// connection time with the completion time of the map, which reaches the missing connections
+    TooLong {
+        time: u32,
+    },
+    InvalidConnection,
+    MissingTime,
+    InvalidMap(ParseIntError),
+    InvalidMapCount(ParseIntError),
+    MapCountTwice,
+    SelfConnection,
+    /// connection time which would be a missing connection
+    MissingConnection {
+        time: u32,
+    },
+    OutsideTable {
+        maps: usize,
+    },
+    /// connection which is already given in `first_line`, and `more` connections after it which are as well
+    Repeated {
+        first_line: usize,
+        first_time: u32,
+        time: u32,
+        more: usize,
+    },
+}
+
+/// Error in a table, with where it is and the text there
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// `None` if the error isn't about one place, like for an empty table
+    pub span: Option<Span>,
+    pub text: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            kind,
+            span: None,
+            text: String::new(),
+        }
+    }
+
+    /// error about the bytes `range` of `line`
+    pub(crate) fn at(
+        kind: ParseErrorKind,
+        line_number: usize,
+        line: &str,
+        range: Range<usize>,
+    ) -> ParseError {
+        ParseError {
+            kind,
+            span: Some(Span::new(line_number, line, range.clone())),
+            text: line[range].to_owned(),
+        }
+    }
+
+    /// The error with the line of `table` it is in, and carets under the text.
+    pub fn render(&self, table: &str) -> String {
+        let Some(span) = self.span else {
+            return self.to_string();
+        };
+        let Some(line) = table.lines().nth(span.line - 1) else {
+            return self.to_string();
+        };
+
+        let number = span.line.to_string();
+        let gutter = " ".repeat(number.len());
+        // keep tabs, so the carets line up with the text above
+        let indent: String = line
+            .chars()
+            .take(span.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let carets = "^".repeat(span.len.max(1));
+        format!("{self}\n{gutter} |\n{number} | {line}\n{gutter} | {indent}{carets}")
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(span) = self.span {
+            write!(f, "line {}, column {}: ", span.line, span.column)?;
+        }
+        let text = &self.text;
+        match &self.kind {
+            ParseErrorKind::Empty => write!(f, "table is empty"),
+            ParseErrorKind::UnknownFormat => write!(
+                f,
+                "expected a row like '[0,169,...]', a header starting with 'map' or a connection like '3 -> 7 : 412'"
+            ),
+            ParseErrorKind::NotAnArray => write!(f, "line is not an array like '[0,169,...]'"),
+            ParseErrorKind::EmptyCell => write!(f, "cell is empty"),
+            ParseErrorKind::InvalidNumber(e) => write!(f, "failed to parse '{text}' as integer: {e}"),
+            ParseErrorKind::RowLength { expected, found } => write!(
+                f,
+                "not every table row has the same length (expected {expected}, got {found})"
+            ),
+            ParseErrorKind::NotSquare { rows, columns } => write!(
+                f,
+                "table has {rows} rows, but {columns} columns (one per map, including the restart column)"
+            ),
+            ParseErrorKind::HeaderLength { expected, found } => write!(
+                f,
+                "header names {found} maps, but the rows are for {expected}"
+            ),
+            ParseErrorKind::NoMaps => write!(f, "table has a header, but no maps"),
+            ParseErrorKind::NoRestartColumn => write!(f, "header has no 'restart' column"),
+            ParseErrorKind::UnknownColumn => write!(
+                f,
+                "unknown column '{text}', expected 'completion', 'category' or 'restart'"
+            ),
+            ParseErrorKind::DuplicateMap => write!(f, "map '{text}' appears twice"),
+            ParseErrorKind::NameMismatch { expected } => write!(
+                f,
+                "row is map '{text}', but the header has '{expected}' in its place"
+            ),
+            ParseErrorKind::TooLong { time } => write!(
+                f,
+                "connection takes {time} frames with completing the map, which is too long"
+            ),
+            ParseErrorKind::InvalidConnection => {
+                write!(f, "expected a connection like '3 -> 7 : 412', got '{text}'")
+            }
+            ParseErrorKind::MissingTime => write!(f, "missing the time after ':' in '{text}'"),
+            ParseErrorKind::InvalidMap(e) => write!(f, "failed to parse '{text}' as map: {e}"),
+            ParseErrorKind::InvalidMapCount(e) => {
+                write!(f, "failed to parse '{text}' as number of maps: {e}")
+            }
+            ParseErrorKind::MapCountTwice => write!(f, "the number of maps is given twice"),
+            ParseErrorKind::SelfConnection => write!(f, "map {text} can't connect to itself"),
+            ParseErrorKind::MissingConnection { time } => write!(
+                f,
+                "connection takes {time}, leave out missing connections instead"
+            ),
+            ParseErrorKind::OutsideTable { maps } => {
+                write!(f, "map {text} is outside of the table (0..{maps})")
+            }
+            ParseErrorKind::Repeated {
+                first_line,
+                first_time,
+                time,
+                more,
+            } => {
+                if time == first_time {
+                    write!(f, "connection is already given in line {first_line}")?;
+                } else {
+                    write!(f, "connection takes {time}, but {first_time} in line {first_line}")?;
+                }
+                if *more > 0 {
+                    write!(f, " (and {more} more connections are given twice)")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::Span;
+    use crate::table::{parse, parse_delimited, DelimitedOptions};
+
+    #[test]
+    fn points_at_the_offending_text() {
+        let table = "[0,169]\n[190, 1x]\n";
+        let error = parse(table).unwrap_err();
+        assert_eq!(
+            error.render(table),
+            "line 2, column 7: failed to parse '1x' as integer: invalid digit found in string\n  \
+             |\n2 | [190, 1x]\n  |       ^^"
+        );
+
+        // columns count characters, not bytes
+        let table = "map restart 1-Ä\nstart 0 169\n1-Ä 190 x\n";
+        let error = parse(table).unwrap_err();
+        assert_eq!(
+            error.span,
+            Some(Span {
+                line: 3,
+                column: 9,
+                len: 1
+            })
+        );
+        assert!(error
+            .render(table)
+            .ends_with("3 | 1-Ä 190 x\n  |         ^"));
+
+        // tabs stay tabs, so the carets line up with the text above
+        let error = parse_delimited("0\t1\n1\tab\n", &DelimitedOptions::tsv()).unwrap_err();
+        assert!(error
+            .render("0\t1\n1\tab\n")
+            .ends_with("2 | 1\tab\n  |  \t^^"));
+
+        let error = parse("\n# nothing\n").unwrap_err();
+        assert_eq!(error.render("\n# nothing\n"), "table is empty");
+    }
+}
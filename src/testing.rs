@@ -0,0 +1,279 @@
+//! Helpers shared by the tests, like random tables which can be reproduced from their seed.
+
+use crate::solver::{
+    self, Connection, Finish, OptionalMap, OptionalMaps, Precedence, SolverSettings,
+};
+use crate::MISSING;
+
+/// xorshift generator, so the tests don't need a dependency and failing tables can be reproduced
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Rng {
+        Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// random number in `0..n`
+    pub(crate) fn below(&mut self, n: u32) -> u32 {
+        (self.next() % n as u64) as u32
+    }
+
+    pub(crate) fn chance(&mut self, percent: u32) -> bool {
+        self.below(100) < percent
+    }
+}
+
+/// Table with `n` maps, where about `missing_percent` of the connections between maps are missing.
+/// Every map can restart, with restart times in a smaller range than the connections, like in real lobbies.
+pub(crate) fn random_table(rng: &mut Rng, n: usize, missing_percent: u32) -> Vec<Vec<u32>> {
+    (0..n)
+        .map(|from| {
+            (0..n)
+                .map(|to| match () {
+                    _ if from == to => 0,
+                    _ if to == 0 => 100 + rng.below(100),
+                    _ if rng.chance(missing_percent) => MISSING,
+                    _ => 100 + rng.below(900),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Settings covering restart limits, several finishes, forced connections, precedences and optional maps,
+/// for a table with at least 4 maps. Settings which don't pass [`SolverSettings::check`] are left out.
+pub(crate) fn settings_variants(n: usize) -> Vec<SolverSettings> {
+    let forced = vec![Connection { from: 1, to: 2 }];
+    let variants = [
+        SolverSettings::default(),
+        SolverSettings {
+            max_restarts: Some(0),
+            ..Default::default()
+        },
+        SolverSettings {
+            max_restarts: Some(1),
+            ..Default::default()
+        },
+        SolverSettings {
+            only_required_restarts: true,
+            ..Default::default()
+        },
+        SolverSettings {
+            restart_penalty: Some(150),
+            ..Default::default()
+        },
+        SolverSettings {
+            finish: Finish::Nodes(vec![n - 1, n - 2]),
+            ..Default::default()
+        },
+        SolverSettings {
+            finish: Finish::Anywhere,
+            ..Default::default()
+        },
+        SolverSettings {
+            forced_connections: forced.clone(),
+            ..Default::default()
+        },
+        SolverSettings {
+            precedences: vec![Precedence {
+                before: 2,
+                after: 1,
+            }],
+            ..Default::default()
+        },
+        SolverSettings {
+            optional_maps: OptionalMaps {
+                maps: vec![OptionalMap {
+                    map: 1,
+                    skip_penalty: 300,
+                }],
+                min_visited: 0,
+            },
+            ..Default::default()
+        },
+        SolverSettings {
+            only_required_restarts: true,
+            finish: Finish::Nodes(vec![n - 1, n - 2]),
+            forced_connections: forced,
+            ..Default::default()
+        },
+    ];
+    variants
+        .into_iter()
+        .filter(|settings| settings.check(n).is_ok())
+        .collect()
+}
+
+/// every route through `table`, sorted by time and then by maps
+pub(crate) fn all_routes(table: &[Vec<u32>], settings: &SolverSettings) -> Vec<(Vec<usize>, u32)> {
+    let mut routes = Vec::new();
+    solver::solve_table(table, settings, |route, time| {
+        routes.push((route.to_vec(), time));
+        u32::MAX
+    })
+    .unwrap();
+    routes.sort_unstable_by(|(a, a_time), (b, b_time)| (a_time, a).cmp(&(b_time, b)));
+    routes
+}
+
+/// Every route through `table`, found by trying each order of the maps with each placement of restarts, without the
+/// solver. Follows the rules of [`SolverSettings`] one by one, so it's only fast enough for a handful of maps.
+/// Sorted like [`all_routes`].
+pub(crate) fn brute_force_routes(
+    table: &[Vec<u32>],
+    settings: &SolverSettings,
+) -> Vec<(Vec<usize>, u32)> {
+    let n = table.len();
+    let mut search = BruteForce {
+        table,
+        settings,
+        forced_next: vec![None; n],
+        forced_previous: vec![None; n],
+        visited: vec![false; n],
+        route: vec![settings.start],
+        routes: Vec::new(),
+    };
+    for &Connection { from, to } in &settings.forced_connections {
+        search.forced_next[from] = Some(to);
+        search.forced_previous[to] = Some(from);
+    }
+    search.visited[settings.start] = true;
+    search.extend(0, 0);
+
+    let mut routes = search.routes;
+    routes.sort_unstable_by(|(a, a_time), (b, b_time)| (a_time, a).cmp(&(b_time, b)));
+    routes
+}
+
+struct BruteForce<'a> {
+    table: &'a [Vec<u32>],
+    settings: &'a SolverSettings,
+    forced_next: Vec<Option<usize>>,
+    forced_previous: Vec<Option<usize>>,
+    visited: Vec<bool>,
+    route: Vec<usize>,
+    routes: Vec<(Vec<usize>, u32)>,
+}
+
+impl BruteForce<'_> {
+    fn time(&self, from: usize, to: usize) -> Option<u32> {
+        let time = self.table[from][to];
+        let forbidden = self
+            .settings
+            .forbidden_connections
+            .contains(&Connection { from, to });
+        (from != to && time < MISSING && !forbidden).then_some(time)
+    }
+
+    /// the penalty replaces the restart time of every map, but forbidden restarts stay forbidden
+    fn restart_time(&self, from: usize) -> Option<u32> {
+        let restart = Connection {
+            from,
+            to: self.settings.start,
+        };
+        if self.settings.forbidden_connections.contains(&restart) {
+            return None;
+        }
+        self.settings
+            .restart_penalty
+            .or_else(|| self.time(restart.from, restart.to))
+    }
+
+    /// whether `to` can be visited next, which needs every connection of its chain of forced connections
+    fn can_visit(&self, from: usize, to: usize) -> bool {
+        let mut chain = to;
+        while let Some(next) = self.forced_next[chain] {
+            if self.time(chain, next).is_none() {
+                return false;
+            }
+            chain = next;
+        }
+        to != self.settings.start
+            && !self.visited[to]
+            && self.time(from, to).is_some()
+            && self.forced_previous[to].is_none_or(|previous| previous == from)
+            && self
+                .settings
+                .precedences
+                .iter()
+                .all(|precedence| precedence.after != to || self.visited[precedence.before])
+    }
+
+    /// whether the route may end at `pos`, if the only finish isn't reached by then
+    fn can_finish_at(&self, pos: usize) -> bool {
+        let forced = self.forced_next[pos].is_some();
+        match &self.settings.finish {
+            Finish::Nodes(finishes) => finishes.len() > 1 && finishes.contains(&pos) && !forced,
+            Finish::Anywhere => pos != self.settings.start && !forced,
+            Finish::Last => false,
+        }
+    }
+
+    /// penalty for the optional maps left out, `None` if the route misses a required map
+    fn skip_penalty(&self) -> Option<u32> {
+        let optional_maps = &self.settings.optional_maps;
+        let mut penalty = 0;
+        let mut optional_visits = 0;
+        for map in (0..self.table.len()).filter(|&map| map != self.settings.start) {
+            let optional = optional_maps
+                .maps
+                .iter()
+                .find(|optional| optional.map == map);
+            match (self.visited[map], optional) {
+                (true, Some(_)) => optional_visits += 1,
+                (true, None) => {}
+                (false, Some(optional)) => penalty += optional.skip_penalty,
+                (false, None) => return None,
+            }
+        }
+        (optional_visits >= optional_maps.min_visited).then_some(penalty)
+    }
+
+    fn finish(&mut self, time: u32) {
+        if let Some(penalty) = self.skip_penalty() {
+            self.routes.push((self.route.clone(), time + penalty));
+        }
+    }
+
+    fn extend(&mut self, time: u32, restarts: u32) {
+        let pos = *self.route.last().unwrap();
+        if Some(pos) == self.settings.finish.single(self.table.len()) {
+            self.finish(time);
+            return;
+        }
+        if self.can_finish_at(pos) {
+            self.finish(time);
+        }
+
+        let mut moved = self.can_finish_at(pos);
+        for to in 0..self.table.len() {
+            if self.forced_next[pos].is_some_and(|next| next != to) || !self.can_visit(pos, to) {
+                continue;
+            }
+            moved = true;
+            self.visited[to] = true;
+            self.route.push(to);
+            self.extend(time + self.table[pos][to], restarts);
+            self.route.pop();
+            self.visited[to] = false;
+        }
+
+        let settings = self.settings;
+        let can_restart = pos != settings.start
+            && self.forced_next[pos].is_none()
+            && settings.max_restarts.is_none_or(|max| restarts < max)
+            && !(settings.only_required_restarts && moved);
+        if let Some(restart_time) = self.restart_time(pos).filter(|_| can_restart) {
+            self.route.push(settings.start);
+            self.extend(time + restart_time, restarts + 1);
+            self.route.pop();
+        }
+    }
+}
@@ -0,0 +1,360 @@
+//! Warnings about tables which parse, but probably contain mistakes, like a typo in a placeholder.
+
+use crate::solver::{Connection, Finish, SettingsError, SolverSettings};
+use crate::table::TableInfo;
+use crate::MISSING;
+
+/// Where in the table a warning is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    Cell(Connection),
+    /// every connection from the map
+    Row(usize),
+    /// every connection to the map
+    Column(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// a map connects to itself, which the solver ignores
+    Diagonal { time: u32 },
+    /// the restart time differs from the `expected` one most maps have
+    UnusualRestart { time: u32, expected: u32 },
+    /// a time like 59999 or 6000, which probably should be a missing connection
+    NearPlaceholder { time: u32 },
+    /// no connection goes to the map
+    Unreachable,
+    /// no connection leaves the map, and it isn't a finish
+    NoExit,
+    /// the time is much lower than the `usual` one to get to the same map,
+    /// and `more` connections after this one are as well, but aren't reported
+    LowTime { time: u32, usual: u32, more: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Warning {
+    pub location: Location,
+    pub kind: WarningKind,
+}
+
+impl Warning {
+    /// Describes the warning with the names of the maps in `table`.
+    pub fn message(&self, table: &TableInfo) -> String {
+        let location = match self.location {
+            Location::Cell(Connection { from, to }) => {
+                format!("{}-{}", table.map_name(from), table.map_name(to))
+            }
+            Location::Row(map) | Location::Column(map) => table.map_name(map),
+        };
+        let from = match self.location {
+            Location::Cell(connection) => connection.from,
+            Location::Row(map) | Location::Column(map) => map,
+        };
+        match self.kind {
+            WarningKind::Diagonal { time } => {
+                format!("{location} takes {time}, but maps don't connect to themselves")
+            }
+            WarningKind::UnusualRestart { time, expected } => {
+                let from = table.map_name(from);
+                format!("restarting from {from} takes {time}, but {expected} from most maps")
+            }
+            WarningKind::NearPlaceholder { time } => {
+                format!("{location} takes {time}, did you mean {MISSING} for a missing connection?")
+            }
+            WarningKind::Unreachable => format!("no connection goes to {location}"),
+            WarningKind::NoExit => format!("no connection leaves {location}"),
+            WarningKind::LowTime { time, usual, more } => {
+                let mut message = format!(
+                    "{location} takes {time}, much less than the usual {usual} to get there"
+                );
+                if more > 0 {
+                    message +=
+                        &format!(" (and {more} more connections are much faster than usual)");
+                }
+                message
+            }
+        }
+    }
+}
+
+/// times below this fraction of the usual time to a map are suspicious, e.g. when a digit is missing
+const LOW_TIME_FRACTION: u32 = 4;
+
+/// how many of the lowest times are reported, big tables have many fast connections which are fine
+const MAX_LOW_TIMES: usize = 5;
+
+/// Looks for mistakes in `table`, which fails like solving it if it doesn't fit `settings`.
+///
+/// The start column holds the restart times, unless `settings` has a restart penalty, and maps which can finish
+/// don't need an exit. Warnings are ordered by their kind, and only the lowest times compared to the usual ones
+/// are reported, with how many more there are.
+pub fn validate(
+    table: &[Vec<u32>],
+    settings: &SolverSettings,
+) -> Result<Vec<Warning>, SettingsError> {
+    settings.check_table(table)?;
+    let n = table.len();
+    let start = settings.start;
+    let is_finish = |map: usize| match &settings.finish {
+        Finish::Last => map == n - 1,
+        Finish::Nodes(finishes) => finishes.contains(&map),
+        Finish::Anywhere => map != start,
+    };
+    let cell = |from, to| Location::Cell(Connection { from, to });
+    let mut warnings = Vec::new();
+
+    for (map, row) in table.iter().enumerate() {
+        if row[map] != 0 {
+            warnings.push(Warning {
+                location: cell(map, map),
+                kind: WarningKind::Diagonal { time: row[map] },
+            });
+        }
+    }
+
+    if settings.restart_penalty.is_none() {
+        // finishes are left out, since the route never restarts from the last one
+        let restarts: Vec<(usize, u32)> = (0..n)
+            .filter(|&map| map != start && !is_finish(map))
+            .map(|map| (map, table[map][start]))
+            .filter(|&(_, time)| time < MISSING)
+            .collect();
+        if let Some(expected) = most_common(restarts.iter().map(|&(_, time)| time)) {
+            for &(map, time) in &restarts {
+                if time != expected {
+                    warnings.push(Warning {
+                        location: cell(map, start),
+                        kind: WarningKind::UnusualRestart { time, expected },
+                    });
+                }
+            }
+        }
+    }
+
+    for (from, row) in table.iter().enumerate() {
+        for (to, &time) in row.iter().enumerate() {
+            let near_placeholder =
+                (MISSING - 1000..MISSING).contains(&time) || time > MISSING || time == MISSING / 10;
+            if near_placeholder {
+                warnings.push(Warning {
+                    location: cell(from, to),
+                    kind: WarningKind::NearPlaceholder { time },
+                });
+            }
+        }
+    }
+
+    let connects = |from: usize, to: usize| from != to && table[from][to] < MISSING;
+    for map in 0..n {
+        if map != start && !(0..n).any(|from| connects(from, map)) {
+            warnings.push(Warning {
+                location: Location::Column(map),
+                kind: WarningKind::Unreachable,
+            });
+        }
+    }
+    for map in 0..n {
+        let can_restart = settings.restart_penalty.is_some() && map != start;
+        if !is_finish(map) && !can_restart && !(0..n).any(|to| connects(map, to)) {
+            warnings.push(Warning {
+                location: Location::Row(map),
+                kind: WarningKind::NoExit,
+            });
+        }
+    }
+
+    let mut low_times = Vec::new();
+    for to in (0..n).filter(|&to| to != start) {
+        let mut times: Vec<u32> = (0..n)
+            .filter(|&from| connects(from, to))
+            .map(|from| table[from][to])
+            .collect();
+        if times.len() < 3 {
+            continue;
+        }
+        times.sort_unstable();
+        let usual = times[times.len() / 2];
+        for from in (0..n).filter(|&from| connects(from, to)) {
+            let time = table[from][to];
+            if time * LOW_TIME_FRACTION < usual {
+                low_times.push((cell(from, to), time, usual));
+            }
+        }
+    }
+    // the lowest compared to the usual time first, which are the most likely typos
+    low_times.sort_by(|&(_, time, usual), &(_, other_time, other_usual)| {
+        (time as u64 * other_usual as u64).cmp(&(other_time as u64 * usual as u64))
+    });
+    let more = low_times.len().saturating_sub(MAX_LOW_TIMES);
+    let reported = low_times.len() - more;
+    for (i, &(location, time, usual)) in low_times[..reported].iter().enumerate() {
+        warnings.push(Warning {
+            location,
+            kind: WarningKind::LowTime {
+                time,
+                usual,
+                more: if i + 1 == reported { more } else { 0 },
+            },
+        });
+    }
+
+    Ok(warnings)
+}
+
+/// the value which appears most often, the lower one on ties
+fn most_common(values: impl Iterator<Item = u32>) -> Option<u32> {
+    let mut values: Vec<u32> = values.collect();
+    values.sort_unstable();
+    values
+        .chunk_by(|a, b| a == b)
+        .max_by_key(|run| (run.len(), std::cmp::Reverse(run[0])))
+        .map(|run| run[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// table without mistakes, where every connection takes 1000 frames and every restart 190
+    fn table(n: usize) -> Vec<Vec<u32>> {
+        (0..n)
+            .map(|from| {
+                (0..n)
+                    .map(|to| match () {
+                        _ if from == to => 0,
+                        _ if to == 0 => 190,
+                        _ => 1000,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn warnings(table: &[Vec<u32>]) -> Vec<Warning> {
+        validate(table, &SolverSettings::default()).unwrap()
+    }
+
+    fn warning(location: Location, kind: WarningKind) -> Warning {
+        Warning { location, kind }
+    }
+
+    fn cell(from: usize, to: usize) -> Location {
+        Location::Cell(Connection { from, to })
+    }
+
+    #[test]
+    fn finds_nothing_in_a_table_without_mistakes() {
+        assert_eq!(warnings(&table(5)), []);
+    }
+
+    #[test]
+    fn finds_maps_connecting_to_themselves() {
+        let mut table = table(5);
+        table[2][2] = 40;
+        let kind = WarningKind::Diagonal { time: 40 };
+        assert_eq!(warnings(&table), [warning(cell(2, 2), kind)]);
+    }
+
+    #[test]
+    fn finds_unusual_restarts() {
+        let mut table = table(5);
+        table[3][0] = 250;
+        // the finish never restarts
+        table[4][0] = 0;
+        let kind = WarningKind::UnusualRestart {
+            time: 250,
+            expected: 190,
+        };
+        assert_eq!(warnings(&table), [warning(cell(3, 0), kind)]);
+
+        let settings = SolverSettings {
+            restart_penalty: Some(100),
+            ..SolverSettings::default()
+        };
+        assert_eq!(validate(&table, &settings).unwrap(), []);
+    }
+
+    #[test]
+    fn finds_times_near_the_placeholder() {
+        let mut table = table(5);
+        table[1][2] = MISSING - 1;
+        table[2][3] = MISSING / 10;
+        assert_eq!(
+            warnings(&table),
+            [
+                warning(
+                    cell(1, 2),
+                    WarningKind::NearPlaceholder { time: MISSING - 1 }
+                ),
+                warning(
+                    cell(2, 3),
+                    WarningKind::NearPlaceholder { time: MISSING / 10 }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_maps_without_a_way_in_or_out() {
+        let mut table = table(5);
+        for row in &mut table {
+            row[2] = MISSING;
+        }
+        table[2][2] = 0;
+        table[3] = vec![MISSING, MISSING, MISSING, 0, MISSING];
+        assert_eq!(
+            warnings(&table),
+            [
+                warning(Location::Column(2), WarningKind::Unreachable),
+                warning(Location::Row(3), WarningKind::NoExit),
+            ]
+        );
+
+        // the finish doesn't need an exit
+        let mut table = self::table(5);
+        table[4] = vec![MISSING, MISSING, MISSING, MISSING, 0];
+        assert_eq!(warnings(&table), []);
+    }
+
+    #[test]
+    fn finds_times_much_lower_than_usual() {
+        let mut table = table(5);
+        table[1][3] = 100;
+        let kind = WarningKind::LowTime {
+            time: 100,
+            usual: 1000,
+            more: 0,
+        };
+        assert_eq!(warnings(&table), [warning(cell(1, 3), kind)]);
+    }
+
+    #[test]
+    fn reports_only_the_lowest_times() {
+        let mut table = table(12);
+        let mut low_times = Vec::new();
+        for (from, row) in table.iter_mut().enumerate().take(4).skip(1) {
+            for (to, time) in row.iter_mut().enumerate().skip(4) {
+                *time = 10 * (to + from) as u32;
+                low_times.push(*time);
+            }
+        }
+        low_times.sort_unstable();
+
+        let warnings = warnings(&table);
+        assert_eq!(warnings.len(), MAX_LOW_TIMES);
+        for (i, warning) in warnings.iter().enumerate() {
+            let WarningKind::LowTime { time, usual, more } = warning.kind else {
+                panic!("{warning:?} isn't a low time");
+            };
+            assert_eq!((time, usual), (low_times[i], 1000));
+            let left_out = low_times.len() - MAX_LOW_TIMES;
+            assert_eq!(more, if i + 1 == MAX_LOW_TIMES { left_out } else { 0 });
+        }
+    }
+
+    #[test]
+    fn rejects_tables_which_arent_square() {
+        let table: Vec<Vec<u32>> = table(3).into_iter().map(|row| row[..2].to_vec()).collect();
+        assert!(validate(&table, &SolverSettings::default()).is_err());
+    }
+}
@@ -1,7 +1,134 @@
 use js_sys::Array;
-use trout::solver::{PossibleConnection, SolverSettings};
+use std::cell::RefCell;
+use trout::route::Route;
+use trout::solver::{
+    CancellationToken, Connection, Finish, OptionalMaps, PossibleConnection, Precedence, Progress,
+    ProgressReporter, SolverSettings, TopSolutions, WarmStart,
+};
+use trout::table::{ParseError, TableInfo};
+use trout::validate::{Location, Warning};
 use wasm_bindgen::prelude::*;
 
+thread_local! {
+    // progress reporters have to be `Send`, which javascript functions are not
+    static PROGRESS_CALLBACK: RefCell<Option<js_sys::Function>> = RefCell::new(None);
+    // cancellation tokens have to be `Send` as well, the page sets the first element to stop the solve
+    static CANCEL_FLAG: RefCell<Option<js_sys::Int32Array>> = RefCell::new(None);
+}
+
+fn is_cancelled() -> bool {
+    CANCEL_FLAG.with(|flag| {
+        flag.borrow()
+            .as_ref()
+            .is_some_and(|flag| js_sys::Atomics::load(flag, 0).is_ok_and(|value| value != 0))
+    })
+}
+
+fn progress_to_js(progress: &Progress) -> js_sys::Object {
+    let obj = js_sys::Object::new();
+    js_sys::Reflect::set(&obj, &"iterations".into(), &progress.iterations.into()).unwrap();
+    js_sys::Reflect::set(&obj, &"cutBranches".into(), &progress.cut_branches.into()).unwrap();
+    js_sys::Reflect::set(&obj, &"bestTime".into(), &progress.best_time.into()).unwrap();
+    js_sys::Reflect::set(&obj, &"lowerBound".into(), &progress.lower_bound.into()).unwrap();
+    js_sys::Reflect::set(&obj, &"explored".into(), &progress.explored.into()).unwrap();
+    obj
+}
+
+/// the route with `names` of its maps, if the table has them
+fn route_to_js(route: &Route, table: &TableInfo) -> js_sys::Object {
+    let maps = route
+        .maps
+        .iter()
+        .copied()
+        .map(JsValue::from)
+        .collect::<Array>();
+    let legs = route
+        .legs
+        .iter()
+        .map(|leg| {
+            let obj = js_sys::Object::new();
+            js_sys::Reflect::set(&obj, &"from".into(), &leg.from.into()).unwrap();
+            js_sys::Reflect::set(&obj, &"to".into(), &leg.to.into()).unwrap();
+            js_sys::Reflect::set(&obj, &"time".into(), &leg.time.into()).unwrap();
+            js_sys::Reflect::set(&obj, &"restart".into(), &leg.restart.into()).unwrap();
+            JsValue::from(obj)
+        })
+        .collect::<Array>();
+
+    let obj = js_sys::Object::new();
+    js_sys::Reflect::set(&obj, &"maps".into(), &maps.into()).unwrap();
+    if table.names.is_some() {
+        let names = route
+            .maps
+            .iter()
+            .map(|&map| JsValue::from(table.map_name(map)))
+            .collect::<Array>();
+        js_sys::Reflect::set(&obj, &"names".into(), &names.into()).unwrap();
+    }
+    js_sys::Reflect::set(&obj, &"legs".into(), &legs.into()).unwrap();
+    js_sys::Reflect::set(&obj, &"restarts".into(), &route.restarts.into()).unwrap();
+    js_sys::Reflect::set(&obj, &"skipPenalty".into(), &route.skip_penalty.into()).unwrap();
+    js_sys::Reflect::set(&obj, &"time".into(), &route.time.into()).unwrap();
+    obj
+}
+
+/// `{message, line, column, length, text}`, where the position is missing if the error isn't about one place
+fn parse_error_to_js(error: &ParseError) -> js_sys::Object {
+    let obj = js_sys::Object::new();
+    js_sys::Reflect::set(&obj, &"message".into(), &error.to_string().into()).unwrap();
+    if let Some(span) = error.span {
+        js_sys::Reflect::set(&obj, &"line".into(), &span.line.into()).unwrap();
+        js_sys::Reflect::set(&obj, &"column".into(), &span.column.into()).unwrap();
+        js_sys::Reflect::set(&obj, &"length".into(), &span.len.into()).unwrap();
+    }
+    js_sys::Reflect::set(&obj, &"text".into(), &error.text.as_str().into()).unwrap();
+    obj
+}
+
+/// parses the table, with errors as objects for the website to highlight
+fn parse_table(table: &str) -> Result<TableInfo, JsValue> {
+    trout::table::parse(table).map_err(|e| parse_error_to_js(&e).into())
+}
+
+/// parses a list like `3:7, 4:9`, where map 3 has to be visited before map 7
+fn parse_precedences(precedences: &str) -> Result<Vec<Precedence>, String> {
+    precedences
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|precedence| !precedence.is_empty())
+        .map(|precedence| precedence.parse().map_err(|e| format!("{:?}", e)))
+        .collect()
+}
+
+/// parses a list like `5:300, 6`, where skipping map 5 costs 300 frames and skipping map 6 is free
+fn parse_optional_maps(optional_maps: &str, min_visited: usize) -> Result<OptionalMaps, String> {
+    let maps = optional_maps
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|optional| !optional.is_empty())
+        .map(|optional| optional.parse().map_err(|e| format!("{:?}", e)))
+        .collect::<Result<_, _>>()?;
+    Ok(OptionalMaps { maps, min_visited })
+}
+
+/// parses `anywhere` or a list of maps like `20, 22`, an empty string is the last map
+fn parse_finish(finish: &str) -> Result<Finish, String> {
+    match finish.trim() {
+        "" => Ok(Finish::Last),
+        finish => finish.parse().map_err(|e| format!("{:?}", e)),
+    }
+}
+
+/// parses a list of chains like `4-5-6, 8-9`
+fn parse_connections(connections: &str) -> Result<Vec<Connection>, String> {
+    let mut parsed = Vec::new();
+    for chain in connections
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|chain| !chain.is_empty())
+    {
+        parsed.extend(Connection::parse_chain(chain).map_err(|e| format!("{:?}", e))?);
+    }
+    Ok(parsed)
+}
+
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
@@ -9,42 +136,21 @@ extern "C" {
 }
 
 fn do_solve(
-    table: &str,
+    table_info: &TableInfo,
     settings: SolverSettings,
     max_solutions: usize,
-    update_solutions: impl Fn(&[usize], u32, usize),
+    update_solutions: impl Fn(&Route, &TableInfo, usize),
 ) -> Result<trout::solver::Stats, anyhow::Error> {
-    let table = trout::parse_table(table)?;
-
-    let mut previous_best = u32::MAX;
-    let mut previous_worst = u32::MAX;
-    let mut best_solutions = Vec::new();
-
-    let stats = trout::solver::solve_table(&table, &settings, |solution, time| {
-        let is_windup = best_solutions.len() < max_solutions;
-
-        if time < previous_worst || is_windup {
-            best_solutions.push((solution.to_vec(), time));
-            best_solutions.sort_by_key(|&(_, time)| time);
-            best_solutions.truncate(max_solutions);
+    let table = &table_info.times;
 
-            let updated_index = best_solutions.iter().position(|&(_, t)| t == time).unwrap();
-
-            update_solutions(&solution, time, updated_index);
-
-            (previous_best, previous_worst) = best_solutions
-                .iter()
-                .fold((std::u32::MAX, std::u32::MIN), |(min, max), &(_, time)| {
-                    (min.min(time), max.max(time))
-                });
-        }
-
-        if is_windup {
-            u32::MAX
-        } else {
-            previous_worst
+    let mut best_solutions = TopSolutions::new(max_solutions);
+    let stats = trout::solver::solve_table(table, &settings, |solution, time| {
+        if let Some(updated_index) = best_solutions.insert(solution, time) {
+            let route = Route::new(table, &settings, solution, time);
+            update_solutions(&route, table_info, updated_index);
         }
-    });
+        best_solutions.worst_time()
+    })?;
 
     Ok(stats)
 }
@@ -55,16 +161,63 @@ pub fn solve(
     max_solutions: usize,
     max_restarts: Option<u32>,
     only_required_restarts: bool,
-    restart_penalty: u32,
+    restart_penalty: Option<u32>,
+    backend: String,
+    time_limit_secs: Option<f64>,
+    warm_start: bool,
+    known_time: Option<u32>,
+    precedences: String,
+    optional_maps: String,
+    min_optional: usize,
+    start: Option<usize>,
+    finish: String,
+    forced: String,
+    forbidden: String,
     callback: &js_sys::Function,
-) -> Result<js_sys::Object, String> {
+    progress_callback: Option<js_sys::Function>,
+    cancel_flag: Option<js_sys::Int32Array>,
+) -> Result<js_sys::Object, JsValue> {
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
 
+    // `std::time::Instant` is not available in the browser
+    let deadline = time_limit_secs.map(|time_limit| js_sys::Date::now() + time_limit * 1000.0);
+    let cancellation = (deadline.is_some() || cancel_flag.is_some()).then(|| {
+        CancellationToken::from_fn(move || {
+            deadline.is_some_and(|deadline| js_sys::Date::now() >= deadline) || is_cancelled()
+        })
+    });
+
     let settings = trout::solver::SolverSettings {
         max_restarts,
         only_required_restarts,
         restart_penalty,
+        backend: backend.parse().map_err(|e| format!("{:?}", e))?,
+        cancellation,
+        progress: progress_callback.is_some().then(|| {
+            ProgressReporter::new(1 << 20, |progress| {
+                PROGRESS_CALLBACK.with(|callback| {
+                    if let Some(callback) = &*callback.borrow() {
+                        let _ = callback.call1(&JsValue::NULL, &progress_to_js(progress));
+                    }
+                })
+            })
+        }),
+        warm_start: WarmStart {
+            heuristic: warm_start,
+            time: known_time,
+            solutions: max_solutions,
+            ..Default::default()
+        },
+        precedences: parse_precedences(&precedences)?,
+        optional_maps: parse_optional_maps(&optional_maps, min_optional)?,
+        start: start.unwrap_or(0),
+        finish: parse_finish(&finish)?,
+        forced_connections: parse_connections(&forced)?,
+        forbidden_connections: parse_connections(&forbidden)?,
+        ..Default::default()
     };
+    PROGRESS_CALLBACK.with(|callback| *callback.borrow_mut() = progress_callback);
+    CANCEL_FLAG.with(|flag| *flag.borrow_mut() = cancel_flag);
 
     if max_solutions == 0 {
         return Err("expected nonzero max amount of solutions".into());
@@ -72,21 +225,15 @@ pub fn solve(
 
     log(&format!("{:?}", settings));
 
+    let table = parse_table(&table)?;
     let stats = do_solve(
         &table,
         settings,
         max_solutions,
-        |solution, time, updated_index| {
-            let route = solution
-                .iter()
-                .copied()
-                .map(JsValue::from)
-                .collect::<Array>();
-
-            let _ = callback.call3(
+        |route, table, updated_index| {
+            let _ = callback.call2(
                 &JsValue::NULL,
-                &time.into(),
-                &route.into(),
+                &route_to_js(route, table).into(),
                 &updated_index.into(),
             );
         },
@@ -97,19 +244,81 @@ pub fn solve(
     js_sys::Reflect::set(&obj, &"iterations".into(), &stats.iterations.into()).unwrap();
     js_sys::Reflect::set(&obj, &"solutions".into(), &stats.solutions_found.into()).unwrap();
     js_sys::Reflect::set(&obj, &"cutBranches".into(), &stats.cut_branches.into()).unwrap();
+    js_sys::Reflect::set(&obj, &"complete".into(), &stats.complete.into()).unwrap();
+    js_sys::Reflect::set(&obj, &"lowerBound".into(), &stats.lower_bound.into()).unwrap();
+    js_sys::Reflect::set(
+        &obj,
+        &"warmStartBound".into(),
+        &stats.warm_start_bound.into(),
+    )
+    .unwrap();
 
     Ok(obj)
 }
 
+/// `{message, from, to}` for each warning, where `to` is missing for rows and `from` for columns
+fn warnings_to_js(table: &TableInfo, warnings: &[Warning]) -> Array {
+    warnings
+        .iter()
+        .map(|warning| {
+            let obj = js_sys::Object::new();
+            let message = warning.message(table);
+            js_sys::Reflect::set(&obj, &"message".into(), &message.into()).unwrap();
+            let (from, to) = match warning.location {
+                Location::Cell(Connection { from, to }) => (Some(from), Some(to)),
+                Location::Row(map) => (Some(map), None),
+                Location::Column(map) => (None, Some(map)),
+            };
+            if let Some(from) = from {
+                js_sys::Reflect::set(&obj, &"from".into(), &from.into()).unwrap();
+            }
+            if let Some(to) = to {
+                js_sys::Reflect::set(&obj, &"to".into(), &to.into()).unwrap();
+            }
+            JsValue::from(obj)
+        })
+        .collect()
+}
+
+#[wasm_bindgen]
+pub fn validate_table(
+    table: String,
+    restart_penalty: Option<u32>,
+    start: Option<usize>,
+    finish: String,
+) -> Result<Array, JsValue> {
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+    let settings = trout::solver::SolverSettings {
+        restart_penalty,
+        start: start.unwrap_or(0),
+        finish: parse_finish(&finish)?,
+        ..Default::default()
+    };
+
+    let table = parse_table(&table)?;
+    let warnings =
+        trout::validate::validate(&table.times, &settings).map_err(|e| format!("{:?}", e))?;
+
+    Ok(warnings_to_js(&table, &warnings))
+}
+
 fn do_suggest(
-    table: &str,
+    table_info: &TableInfo,
     settings: SolverSettings,
     time_to_beat: u32,
-    emit_solution: impl Fn(PossibleConnection<'_>),
+    emit_solution: impl Fn(PossibleConnection, &TableInfo),
+    report_progress: impl Fn(usize, usize),
 ) -> Result<(), anyhow::Error> {
-    let table = trout::parse_table(table)?;
-
-    trout::solver::find_new_connections(&table, &settings, time_to_beat, emit_solution);
+    // the browser runs everything on the worker's thread
+    trout::solver::find_new_connections(
+        &table_info.times,
+        &settings,
+        time_to_beat,
+        1,
+        |possible_connection| emit_solution(possible_connection, table_info),
+        report_progress,
+    )?;
 
     Ok(())
 }
@@ -119,35 +328,100 @@ pub fn suggest_solutions(
     table: String,
     max_restarts: Option<u32>,
     only_required_restarts: bool,
-    restart_penalty: u32,
+    restart_penalty: Option<u32>,
+    backend: String,
+    precedences: String,
+    optional_maps: String,
+    min_optional: usize,
+    start: Option<usize>,
+    finish: String,
+    forced: String,
+    forbidden: String,
     time_to_beat: u32,
     callback: &js_sys::Function,
-) -> Result<(), String> {
+    progress_callback: Option<js_sys::Function>,
+) -> Result<(), JsValue> {
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
 
     let settings = trout::solver::SolverSettings {
         max_restarts,
         only_required_restarts,
         restart_penalty,
+        backend: backend.parse().map_err(|e| format!("{:?}", e))?,
+        precedences: parse_precedences(&precedences)?,
+        optional_maps: parse_optional_maps(&optional_maps, min_optional)?,
+        start: start.unwrap_or(0),
+        finish: parse_finish(&finish)?,
+        forced_connections: parse_connections(&forced)?,
+        forbidden_connections: parse_connections(&forbidden)?,
+        ..Default::default()
     };
 
-    do_suggest(&table, settings, time_to_beat, |possible_connection| {
-        let route = possible_connection
-            .path
-            .iter()
-            .copied()
-            .map(JsValue::from)
-            .collect::<Array>();
-
-        let args = js_sys::Array::from_iter([
-            JsValue::from(possible_connection.start),
-            JsValue::from(possible_connection.end),
-            JsValue::from(possible_connection.time),
-            JsValue::from(route),
-        ]);
-        let _ = callback.apply(&JsValue::NULL, &args);
-    })
+    let table = parse_table(&table)?;
+    do_suggest(
+        &table,
+        settings,
+        time_to_beat,
+        |possible_connection, table| {
+            let _ = callback.call3(
+                &JsValue::NULL,
+                &possible_connection.start.into(),
+                &possible_connection.end.into(),
+                &route_to_js(&possible_connection.route, table).into(),
+            );
+        },
+        |tested, total| {
+            if let Some(progress_callback) = &progress_callback {
+                let _ = progress_callback.call2(&JsValue::NULL, &tested.into(), &total.into());
+            }
+        },
+    )
     .map_err(|e| format!("{:?}", e))?;
 
     Ok(())
 }
+
+fn do_evaluate(
+    table: &TableInfo,
+    settings: SolverSettings,
+    route: &str,
+) -> Result<Route, anyhow::Error> {
+    let route = trout::route::parse_route(route, settings.start, table.names.as_deref())?;
+    trout::route::evaluate_route(&table.times, &settings, &route)
+}
+
+#[wasm_bindgen]
+pub fn evaluate_route(
+    table: String,
+    max_restarts: Option<u32>,
+    only_required_restarts: bool,
+    restart_penalty: Option<u32>,
+    precedences: String,
+    optional_maps: String,
+    min_optional: usize,
+    start: Option<usize>,
+    finish: String,
+    forced: String,
+    forbidden: String,
+    route: String,
+) -> Result<js_sys::Object, JsValue> {
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+    let settings = trout::solver::SolverSettings {
+        max_restarts,
+        only_required_restarts,
+        restart_penalty,
+        precedences: parse_precedences(&precedences)?,
+        optional_maps: parse_optional_maps(&optional_maps, min_optional)?,
+        start: start.unwrap_or(0),
+        finish: parse_finish(&finish)?,
+        forced_connections: parse_connections(&forced)?,
+        forbidden_connections: parse_connections(&forbidden)?,
+        ..Default::default()
+    };
+
+    let table = parse_table(&table)?;
+    let route = do_evaluate(&table, settings, &route).map_err(|e| format!("{:?}", e))?;
+
+    Ok(route_to_js(&route, &table))
+}
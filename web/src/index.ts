@@ -1,14 +1,24 @@
-import { solve, setOnSolutions, suggest, setOnSuggestion } from "./solver";
-import type { Solution, Suggestion } from "./worker";
+import { solve, stopSolve, setOnSolutions, suggest, setOnSuggestion, setOnProgress, setOnSuggestProgress, setOnWarnings, evaluate } from "./solver";
+import type { SolverError } from "./solver";
+import type { Backend, Leg, Route, Suggestion } from "./worker";
 import "./theme";
 
 let solveBtn = document.getElementById("solveBtn")!;
+let stopBtn = document.getElementById("stopBtn")! as HTMLButtonElement;
 let solveOutputList = document.getElementById("solveOutputList")!;
 let solveLoadingIndicator = document.getElementById("solveLoadingIndicator")!;
+let solveProgress = document.getElementById("solveProgress") as HTMLProgressElement;
+let solveProgressMessage = document.getElementById("solveProgressMessage")!;
 let suggestBtn = document.getElementById("suggestBtn")! as HTMLButtonElement;
 let suggestOutputList = document.getElementById("suggestOutputList")!;
 let suggestLoadingIndicator = document.getElementById("suggestLoadingIndicator")!;
+let suggestProgress = document.getElementById("suggestProgress") as HTMLProgressElement;
+let suggestProgressMessage = document.getElementById("suggestProgressMessage")!;
+let evaluateBtn = document.getElementById("evaluateBtn")!;
+let evaluateMessage = document.getElementById("evaluateMessage")!;
+let evaluateOutputList = document.getElementById("evaluateOutputList")!;
 let errorMessage = document.getElementById("errorMessage")!;
+let warningsList = document.getElementById("warningsList")!;
 let statsMessage = document.getElementById("statsMessage")!;
 let settingsForm = document.getElementById("settings")!;
 
@@ -17,9 +27,22 @@ let inputNSolutions = document.getElementById("nSolutions") as HTMLInputElement;
 let inputMaxRestarts = document.getElementById("maxRestarts") as HTMLInputElement;
 let inputOnlyRequiredRestarts = document.getElementById("onlyRequiredRestarts") as HTMLInputElement;
 let inputRestartPenalty = document.getElementById("restartPenalty") as HTMLInputElement;
+let inputBackend = document.getElementById("backend") as HTMLSelectElement;
+let inputTimeLimit = document.getElementById("timeLimit") as HTMLInputElement;
+let inputWarmStart = document.getElementById("warmStart") as HTMLInputElement;
+let inputKnownTime = document.getElementById("knownTime") as HTMLInputElement;
+let inputPrecedences = document.getElementById("precedences") as HTMLInputElement;
+let inputOptionalMaps = document.getElementById("optionalMaps") as HTMLInputElement;
+let inputMinOptional = document.getElementById("minOptional") as HTMLInputElement;
+let inputStart = document.getElementById("start") as HTMLInputElement;
+let inputFinish = document.getElementById("finish") as HTMLInputElement;
+let inputForced = document.getElementById("forced") as HTMLInputElement;
+let inputForbidden = document.getElementById("forbidden") as HTMLInputElement;
 let inputSuggestThreshold = document.getElementById("suggestFrameThreshold") as HTMLInputElement;
+let inputEvaluateRoute = document.getElementById("evaluateRoute") as HTMLInputElement;
 
 suggestBtn.disabled = true;
+stopBtn.disabled = true;
 
 let bestSolution: number | undefined = undefined;
 
@@ -34,39 +57,33 @@ inputTimeTable.addEventListener("keypress", (e) => {
 let setSolverSpinning = (active: boolean) => solveLoadingIndicator.classList.toggle("disabled", !active);
 let setSuggestSpinning = (active: boolean) => suggestLoadingIndicator.classList.toggle("disabled", !active);
 
-function createRouteElement(route: number[]): HTMLElement {
-    let routeEl = document.createElement("code");
-
-    let routeElements = route.flatMap<Element | string>((number, index) => {
-        if (index == 0) return [];
-
-        let elements: (Element | string)[] = [];
+// name of a map in the route, if the table has names
+function mapName(route: Route, map: number): string {
+    return route.names?.[route.maps.indexOf(map)] ?? `${map}`;
+}
 
-        let pad = false;
+function createRouteElement(route: Route): HTMLElement {
+    let routeEl = document.createElement("code");
 
-        if (number === 0) {
+    let routeElements = route.legs.map<Element | string>(leg => {
+        if (leg.restart) {
             let restart = document.createElement("span");
             restart.textContent = "[R] ";
             restart.className = "restart";
-            elements.push(restart);
-        } else {
-            let num = number.toString();
-            elements.push(pad ? num.padEnd(3) : (num + " "));
-            length = num.length;
+            return restart;
         }
-
-        return elements;
+        return `${mapName(route, leg.to)} `;
     });
     routeEl.replaceChildren(...routeElements);
     return routeEl;
 }
 
-function createSolutionLi(solution: Solution): HTMLLIElement {
+function createSolutionLi(route: Route): HTMLLIElement {
     let timeEl = document.createElement("span");
     timeEl.className = "time";
-    timeEl.textContent = `${formatDuration(solution.time * 17, true)} (${solution.time}): `;
+    timeEl.textContent = `${formatDuration(route.time * 17, true)} (${route.time}): `;
 
-    let routeEl = createRouteElement(solution.route);
+    let routeEl = createRouteElement(route);
 
     let li = document.createElement("li");
     li.className = "newSolution";
@@ -75,6 +92,25 @@ function createSolutionLi(solution: Solution): HTMLLIElement {
     return li;
 }
 
+// shows the error, and selects the place in the table it is about
+function showError(error: SolverError) {
+    errorMessage.textContent = `Error: ${error.message}`;
+    console.error(error);
+
+    let tableError = error.tableError;
+    if (tableError?.line === undefined) {
+        return;
+    }
+    let lines = inputTimeTable.value.split("\n");
+    let lineStart = lines.slice(0, tableError.line - 1).reduce((start, line) => start + line.length + 1, 0);
+    let line = lines[tableError.line - 1] ?? "";
+    // columns count characters, but the textarea counts UTF-16 units
+    let start = lineStart + Array.from(line).slice(0, tableError.column! - 1).join("").length;
+    let length = Array.from(line).slice(tableError.column! - 1, tableError.column! - 1 + tableError.length!).join("").length;
+    inputTimeTable.focus();
+    inputTimeTable.setSelectionRange(start, length > 0 ? start + length : lineStart + line.length);
+}
+
 function truncateChildren(element: Element, length: number) {
     while (element.childElementCount > length) {
         element.removeChild(element.lastChild!);
@@ -94,10 +130,10 @@ function createSuggestionLi(suggestion: Suggestion): HTMLLIElement {
         throw new Error("attempted to suggest new drafts without solving first");
     }
 
-    let frameDifference = bestSolution - suggestion.time;
+    let frameDifference = bestSolution - suggestion.route.time;
 
     let nodeEl = document.createElement("span");
-    nodeEl.textContent = `${suggestion.start}-${suggestion.end}`;
+    nodeEl.textContent = `${mapName(suggestion.route, suggestion.start)}-${mapName(suggestion.route, suggestion.end)}`;
     nodeEl.className = "suggestConnection";
 
     let timeEl = document.createElement("span");
@@ -114,24 +150,62 @@ function createSuggestionLi(suggestion: Suggestion): HTMLLIElement {
 }
 
 
+function createLegLi(route: Route, leg: Leg): HTMLLIElement {
+    let from = mapName(route, leg.from);
+    let nodeEl = document.createElement("code");
+    nodeEl.textContent = leg.restart ? `${from}-[R]` : `${from}-${mapName(route, leg.to)}`;
+
+    let timeEl = document.createElement("span");
+    timeEl.className = "time";
+    timeEl.textContent = `${leg.time}`;
+
+    let li = document.createElement("li");
+    li.replaceChildren(nodeEl, ": ", timeEl);
 
-setOnSolutions((solution, updatedIndex) => {
+    return li;
+}
+
+
+setOnSolutions((route, updatedIndex) => {
     let nSolutions = Number(inputNSolutions.value);
 
-    if (bestSolution === undefined || solution.time < bestSolution) {
-        bestSolution = solution.time;
+    if (bestSolution === undefined || route.time < bestSolution) {
+        bestSolution = route.time;
     }
 
-    let li = createSolutionLi(solution);
+    let li = createSolutionLi(route);
     insertChildAt(solveOutputList, li, updatedIndex);
     truncateChildren(solveOutputList, nSolutions);
 });
 
+setOnProgress((progress) => {
+    solveProgress.value = progress.explored;
+
+    let msg = `${(progress.explored * 100).toFixed(1)}% explored`;
+    if (progress.bestTime !== undefined) {
+        msg += `, best route is at most ${progress.bestTime - progress.lowerBound} frames slower than optimal`;
+    }
+    solveProgressMessage.textContent = msg;
+});
+
 setOnSuggestion((suggestion) => {
     let li = createSuggestionLi(suggestion);
     suggestOutputList.appendChild(li);
 });
 
+setOnWarnings((warnings) => {
+    warningsList.replaceChildren(...warnings.map(warning => {
+        let li = document.createElement("li");
+        li.textContent = `Warning: ${warning.message}`;
+        return li;
+    }));
+});
+
+setOnSuggestProgress((tested, total) => {
+    suggestProgress.value = total > 0 ? tested / total : 1;
+    suggestProgressMessage.textContent = `${tested}/${total} connections tested`;
+});
+
 function formatDuration(millis: number, alwaysIncludeMinutes?: boolean) {
     let ms = millis % 1000;
     millis = (millis - ms) / 1000;
@@ -151,23 +225,41 @@ function formatDuration(millis: number, alwaysIncludeMinutes?: boolean) {
     return str;
 }
 
+// whether the running solve was stopped with the stop button
+let stopped = false;
+
 solveBtn.addEventListener("click", () => {
     setSolverSpinning(true);
     setSuggestSpinning(false);
     suggestBtn.disabled = true;
+    stopBtn.disabled = false;
+    stopped = false;
     bestSolution = undefined;
 
     solveOutputList.replaceChildren();
     suggestOutputList.replaceChildren();
+    solveProgress.removeAttribute("value");
+    solveProgressMessage.textContent = "";
     statsMessage.textContent = "";
     errorMessage.textContent = "";
+    warningsList.replaceChildren();
 
     let table = inputTimeTable.value;
     let maxSolutions = Number(inputNSolutions.value);
     let maxRestarts = inputMaxRestarts.value !== "" ? Number(inputMaxRestarts.value) : undefined;
     let onlyRequiredRestarts = inputOnlyRequiredRestarts.checked;
-    // let restartPenalty = Number(inputRestartPenalty.value);
-    let restartPenalty = 190;
+    let restartPenalty = inputRestartPenalty.value !== "" ? Number(inputRestartPenalty.value) : undefined;
+    let backend = inputBackend.value as Backend;
+    let timeLimit = inputTimeLimit.value !== "" ? Number(inputTimeLimit.value) : undefined;
+    let warmStart = inputWarmStart.checked;
+    let knownTime = inputKnownTime.value !== "" ? Number(inputKnownTime.value) : undefined;
+    let precedences = inputPrecedences.value;
+    let optionalMaps = inputOptionalMaps.value;
+    let minOptional = inputMinOptional.value !== "" ? Number(inputMinOptional.value) : 0;
+    let startMap = inputStart.value !== "" ? Number(inputStart.value) : undefined;
+    let finishMaps = inputFinish.value;
+    let forced = inputForced.value;
+    let forbidden = inputForbidden.value;
 
     let start = Date.now();
     solve({
@@ -176,27 +268,54 @@ solveBtn.addEventListener("click", () => {
         maxRestarts,
         onlyRequiredRestarts,
         restartPenalty,
+        backend,
+        timeLimit,
+        warmStart,
+        knownTime,
+        precedences,
+        optionalMaps,
+        minOptional,
+        start: startMap,
+        finish: finishMaps,
+        forced,
+        forbidden,
     })
         .then(stats => {
             let end = Date.now();
 
             if (stats) {
                 let msg = `${stats.solutions} solutions found, ${stats.iterations} calls to pathfind function and ${stats.cutBranches} cut branches, in ${formatDuration(end - start, true)} `;
+                if (stats.warmStartBound !== undefined) {
+                    msg += `(started with a bound of ${stats.warmStartBound}) `;
+                }
+                if (!stats.complete) {
+                    msg += bestSolution !== undefined
+                        ? `(stopped early, the best route is at most ${bestSolution - Math.min(stats.lowerBound, bestSolution)} frames slower than optimal)`
+                        : `(stopped early, no route is faster than ${stats.lowerBound})`;
+                }
                 statsMessage.textContent = msg;
+            } else if (stopped) {
+                statsMessage.textContent = "Stopped, the routes above are the best ones found so far";
             } else {
                 statsMessage.textContent = "Code not fully loaded yet, try again later";
             }
             suggestBtn.disabled = false;
         })
-        .catch((error: Error) => {
-            errorMessage.textContent = `Error: ${error.message}`;
-            console.error(error);
+        .catch((error: SolverError) => {
+            showError(error);
         })
         .finally(() => {
             setSolverSpinning(false);
+            stopBtn.disabled = true;
         });
 });
 
+stopBtn.addEventListener("click", () => {
+    stopped = true;
+    stopBtn.disabled = true;
+    stopSolve();
+});
+
 suggestBtn.addEventListener("click", () => {
     if (bestSolution === undefined) {
         throw new Error("attempted to suggest new drafts without solving first");
@@ -205,24 +324,71 @@ suggestBtn.addEventListener("click", () => {
     setSuggestSpinning(true);
 
     suggestOutputList.replaceChildren();
+    suggestProgress.removeAttribute("value");
+    suggestProgressMessage.textContent = "";
 
     let table = inputTimeTable.value;
     let maxRestarts = inputMaxRestarts.value !== "" ? Number(inputMaxRestarts.value) : undefined;
     let onlyRequiredRestarts = inputOnlyRequiredRestarts.checked;
-    // let restartPenalty = Number(inputRestartPenalty.value);
-    let restartPenalty = 190;
+    let restartPenalty = inputRestartPenalty.value !== "" ? Number(inputRestartPenalty.value) : undefined;
+    let backend = inputBackend.value as Backend;
+    let precedences = inputPrecedences.value;
+    let optionalMaps = inputOptionalMaps.value;
+    let minOptional = inputMinOptional.value !== "" ? Number(inputMinOptional.value) : 0;
+    let startMap = inputStart.value !== "" ? Number(inputStart.value) : undefined;
+    let finishMaps = inputFinish.value;
+    let forced = inputForced.value;
+    let forbidden = inputForbidden.value;
 
     let frameThreshold = Number(inputSuggestThreshold.value);
     let timeToBeat = bestSolution - frameThreshold;
 
     suggest({
-        table, maxRestarts, onlyRequiredRestarts, restartPenalty, timeToBeat
+        table, maxRestarts, onlyRequiredRestarts, restartPenalty, backend, precedences, optionalMaps, minOptional, start: startMap, finish: finishMaps, forced, forbidden, timeToBeat
     })
-        .catch((error: Error) => {
-            errorMessage.textContent = `Error: ${error.message}`;
-            console.error(error);
+        .catch((error: SolverError) => {
+            showError(error);
         })
         .finally(() => {
             setSuggestSpinning(false);
         });
-});
\ No newline at end of file
+});
+
+evaluateBtn.addEventListener("click", () => {
+    evaluateOutputList.replaceChildren();
+    evaluateMessage.textContent = "";
+    errorMessage.textContent = "";
+
+    let table = inputTimeTable.value;
+    let maxRestarts = inputMaxRestarts.value !== "" ? Number(inputMaxRestarts.value) : undefined;
+    let onlyRequiredRestarts = inputOnlyRequiredRestarts.checked;
+    let restartPenalty = inputRestartPenalty.value !== "" ? Number(inputRestartPenalty.value) : undefined;
+    let precedences = inputPrecedences.value;
+    let optionalMaps = inputOptionalMaps.value;
+    let minOptional = inputMinOptional.value !== "" ? Number(inputMinOptional.value) : 0;
+    let startMap = inputStart.value !== "" ? Number(inputStart.value) : undefined;
+    let finishMaps = inputFinish.value;
+    let forced = inputForced.value;
+    let forbidden = inputForbidden.value;
+    let route = inputEvaluateRoute.value;
+
+    evaluate({
+        table, maxRestarts, onlyRequiredRestarts, restartPenalty, precedences, optionalMaps, minOptional, start: startMap, finish: finishMaps, forced, forbidden, route
+    })
+        .then(evaluated => {
+            if (!evaluated) {
+                evaluateMessage.textContent = "Code not fully loaded yet, try again later";
+                return;
+            }
+
+            let msg = `Route takes ${formatDuration(evaluated.time * 17, true)} (${evaluated.time}) with ${evaluated.restarts} restarts`;
+            if (evaluated.skipPenalty > 0) {
+                msg += `, including ${evaluated.skipPenalty} for skipped maps`;
+            }
+            evaluateMessage.textContent = msg;
+            evaluateOutputList.replaceChildren(...evaluated.legs.map(leg => createLegLi(evaluated, leg)));
+        })
+        .catch((error: SolverError) => {
+            showError(error);
+        });
+});
@@ -1,15 +1,33 @@
-import type { SolveParams, Solution, Stats, WorkerRequest, WorkerResponse, SuggestParams, Suggestion } from "./worker";
+import type { SolveParams, Route, Stats, WorkerRequest, WorkerResponse, SuggestParams, Suggestion, Progress, EvaluateParams, TableError, TableWarning } from "./worker";
 
-let onSolutionCallback: (solution: Solution, updatedIndex: number) => void;
-export function setOnSolutions(onSolution: (solution: Solution, updatedIndex: number) => void) {
+// error of the solver, with where it is if the table couldn't be parsed
+export type SolverError = Error & { tableError?: TableError; };
+
+let onSolutionCallback: (route: Route, updatedIndex: number) => void;
+export function setOnSolutions(onSolution: (route: Route, updatedIndex: number) => void) {
     onSolutionCallback = onSolution;
 }
 
+let onWarningsCallback: (warnings: TableWarning[]) => void;
+export function setOnWarnings(onWarnings: (warnings: TableWarning[]) => void) {
+    onWarningsCallback = onWarnings;
+}
+
+let onProgressCallback: (progress: Progress) => void;
+export function setOnProgress(onProgress: (progress: Progress) => void) {
+    onProgressCallback = onProgress;
+}
+
 let onSuggestionCallback: (suggestion: Suggestion) => void;
 export function setOnSuggestion(onSuggestion: (suggestion: Suggestion) => void) {
     onSuggestionCallback = onSuggestion;
 }
 
+let onSuggestProgressCallback: (tested: number, total: number) => void;
+export function setOnSuggestProgress(onSuggestProgress: (tested: number, total: number) => void) {
+    onSuggestProgressCallback = onSuggestProgress;
+}
+
 type WorkerState = {
     initialized: false,
     runningSolve: false,
@@ -56,19 +74,27 @@ let loadWorker = () => createInitializedWorker().then(worker => {
     };
 });
 
-let onFinishSolve = (stats: Stats) => { };
+let onFinishSolve = (stats: Stats | undefined) => { };
 let onFinishSuggest = () => { };
-let onError: (error: Error) => void = (e) => { };
+let onEvaluated = (route: Route) => { };
+let onEvaluateError = (error: SolverError) => { };
+let onError: (error: SolverError) => void = (e) => { };
 
 function workerHandler(message: MessageEvent<WorkerResponse>) {
     if (message.data.eventType == "INITIALIZED") {
         throw new Error("double initialization");
     } else if (message.data.eventType == "EMIT") {
-        onSolutionCallback(message.data.solution, message.data.updatedIndex);
+        onSolutionCallback(message.data.route, message.data.updatedIndex);
+    } else if (message.data.eventType == "WARNINGS") {
+        onWarningsCallback(message.data.warnings);
+    } else if (message.data.eventType == "PROGRESS") {
+        onProgressCallback(message.data.progress);
     } else if (message.data.eventType == "EMIT_SUGGESTION") {
         onSuggestionCallback(message.data.suggestion);
+    } else if (message.data.eventType == "SUGGEST_PROGRESS") {
+        onSuggestProgressCallback(message.data.tested, message.data.total);
     } else if (message.data.eventType == "ERROR") {
-        onError(message.data.error);
+        onError(Object.assign(message.data.error, { tableError: message.data.tableError }));
     } else if (message.data.eventType == "FINISH") {
         console.timeEnd("solve");
         workerState.runningSolve = false;
@@ -77,6 +103,10 @@ function workerHandler(message: MessageEvent<WorkerResponse>) {
         console.timeEnd("suggest");
         workerState.runningSuggest = false;
         onFinishSuggest();
+    } else if (message.data.eventType == "EVALUATED") {
+        onEvaluated(message.data.route);
+    } else if (message.data.eventType == "EVALUATE_ERROR") {
+        onEvaluateError(Object.assign(message.data.error, { tableError: message.data.tableError }));
     } else {
         let _: never = message.data;
     }
@@ -101,9 +131,12 @@ export function solve(params: SolveParams): Promise<Stats | undefined> {
 
     console.time("solve");
     workerState.runningSolve = true;
+    // the worker is busy while solving, so it can only see a stop through shared memory
+    cancelFlag = self.crossOriginIsolated ? new Int32Array(new SharedArrayBuffer(4)) : undefined;
     let message: WorkerRequest = {
         eventType: "CALL",
         params,
+        cancelFlag,
     };
     workerState.worker.postMessage(message);
 
@@ -113,6 +146,26 @@ export function solve(params: SolveParams): Promise<Stats | undefined> {
     });
 }
 
+let cancelFlag: Int32Array | undefined;
+
+// Stops the running solve, which then finishes with the stats of the routes found so far.
+// Without shared memory the worker is terminated instead, and the solve finishes without stats.
+export function stopSolve() {
+    if (!workerState.initialized || !workerState.runningSolve) {
+        return;
+    }
+
+    if (cancelFlag) {
+        Atomics.store(cancelFlag, 0, 1);
+        return;
+    }
+
+    console.timeEnd("solve");
+    workerState.worker.terminate();
+    let finish = onFinishSolve;
+    loadWorker().then(() => finish(undefined));
+}
+
 export function suggest(params: SuggestParams): Promise<void> {
     if (!workerState.initialized) {
         console.warn("attempted to solve before initialization");
@@ -141,3 +194,22 @@ export function suggest(params: SuggestParams): Promise<void> {
         onError = reject;
     });
 }
+
+// evaluating is quick, so it waits for a running request instead of terminating it
+export function evaluate(params: EvaluateParams): Promise<Route | undefined> {
+    if (!workerState.initialized) {
+        console.warn("attempted to evaluate before initialization");
+        return Promise.resolve(undefined);
+    }
+
+    let message: WorkerRequest = {
+        eventType: "EVALUATE",
+        params,
+    };
+    workerState.worker.postMessage(message);
+
+    return new Promise((resolve, reject) => {
+        onEvaluated = resolve;
+        onEvaluateError = reject;
+    });
+}
@@ -1,44 +1,89 @@
-import init, { solve, suggest_solutions } from "../solver_wasm/out/solver_wasm";
+import init, { solve, suggest_solutions, evaluate_route, validate_table } from "../solver_wasm/out/solver_wasm";
 
 export type WorkerRequest = {
     eventType: "INITIALIZE";
 } | {
     eventType: "CALL",
     params: SolveParams,
+    // shared with the page, which sets it to stop the solve
+    cancelFlag?: Int32Array,
 } | {
     eventType: "SUGGEST",
     params: SuggestParams,
+} | {
+    eventType: "EVALUATE",
+    params: EvaluateParams,
 };
 export type WorkerResponse = {
     eventType: "INITIALIZED";
 } | {
     eventType: "EMIT",
-    solution: Solution;
+    route: Route;
     updatedIndex: number,
+} | {
+    eventType: "WARNINGS",
+    warnings: TableWarning[],
+} | {
+    eventType: "PROGRESS",
+    progress: Progress,
 } | {
     eventType: "EMIT_SUGGESTION",
     suggestion: Suggestion;
+} | {
+    eventType: "SUGGEST_PROGRESS",
+    tested: number,
+    total: number,
 } | {
     eventType: "ERROR",
     error: Error,
+    tableError?: TableError,
 } | {
     eventType: "FINISH";
     stats: Stats,
 } | {
     eventType: "FINISH_SUGGESTION";
+} | {
+    eventType: "EVALUATED",
+    route: Route,
+} | {
+    eventType: "EVALUATE_ERROR",
+    error: Error,
+    tableError?: TableError,
 };
 
 export type Stats = {
     iterations: number;
     solutions: number;
     cutBranches: number;
+    complete: boolean;
+    lowerBound: number;
+    warmStartBound: number | undefined;
+};
+
+export type Progress = {
+    iterations: number;
+    cutBranches: number;
+    bestTime: number | undefined;
+    lowerBound: number;
+    explored: number;
 };
 
 export type SolveParams = {
     table: string,
     maxRestarts: number | undefined,
     onlyRequiredRestarts: boolean,
-    restartPenalty: number,
+    restartPenalty: number | undefined,
+    backend: Backend,
+    timeLimit: number | undefined,
+    warmStart: boolean,
+    knownTime: number | undefined,
+    precedences: string,
+    optionalMaps: string,
+    minOptional: number,
+    start: number | undefined,
+    finish: string,
+    forced: string,
+    forbidden: string,
 
     maxSolutions: number;
 };
@@ -46,35 +91,81 @@ export type SuggestParams = {
     table: string,
     maxRestarts: number | undefined,
     onlyRequiredRestarts: boolean,
-    restartPenalty: number,
+    restartPenalty: number | undefined,
+    backend: Backend,
+    precedences: string,
+    optionalMaps: string,
+    minOptional: number,
+    start: number | undefined,
+    finish: string,
+    forced: string,
+    forbidden: string,
 
     timeToBeat: number;
 };
 
-export type Solution = { time: number, route: number[]; };
-export type Suggestion = { start: number, end: number, time: number, route: number[]; };
+export type EvaluateParams = {
+    table: string,
+    maxRestarts: number | undefined,
+    onlyRequiredRestarts: boolean,
+    restartPenalty: number | undefined,
+    precedences: string,
+    optionalMaps: string,
+    minOptional: number,
+    start: number | undefined,
+    finish: string,
+    forced: string,
+    forbidden: string,
+
+    route: string;
+};
+
+export type Backend = "branch-and-bound" | "held-karp";
+
+export type Leg = { from: number, to: number, time: number, restart: boolean; };
+export type Route = { maps: number[], names?: string[], legs: Leg[], restarts: number, skipPenalty: number, time: number; };
+export type Suggestion = { start: number, end: number, route: Route; };
+// where the table couldn't be parsed, the position is missing if the error isn't about one place
+export type TableError = { message: string, line?: number, column?: number, length?: number, text: string; };
+// suspicious part of the table, `to` is missing for rows and `from` for columns
+export type TableWarning = { message: string, from?: number, to?: number; };
 
 
 function post(message: WorkerResponse) {
     self.postMessage(message);
 }
 
+// the solver throws strings, or objects for errors in the table
+function toError(error: any): { error: Error, tableError?: TableError; } {
+    if (typeof error === "object" && error !== null && "message" in error) {
+        return { error: new Error(error.message), tableError: error as TableError };
+    }
+    return { error: new Error(error) };
+}
+
 self.addEventListener("message", (message: MessageEvent<WorkerRequest>) => {
     if (message.data.eventType == "INITIALIZE") {
         init().then(() => post({ eventType: "INITIALIZED" }));
     } else if (message.data.eventType == "CALL") {
-        let { table, maxSolutions, maxRestarts, onlyRequiredRestarts, restartPenalty } = message.data.params;
+        let { table, maxSolutions, maxRestarts, onlyRequiredRestarts, restartPenalty, backend, timeLimit, warmStart, knownTime, precedences, optionalMaps, minOptional, start, finish, forced, forbidden } = message.data.params;
 
         try {
-            let stats = solve(table, maxSolutions, maxRestarts, onlyRequiredRestarts, restartPenalty, (time: number, route: number[], updatedIndex: number) => {
+            post({
+                eventType: "WARNINGS",
+                warnings: validate_table(table, restartPenalty, start, finish) as TableWarning[],
+            });
+            let stats = solve(table, maxSolutions, maxRestarts, onlyRequiredRestarts, restartPenalty, backend, timeLimit, warmStart, knownTime, precedences, optionalMaps, minOptional, start, finish, forced, forbidden, (route: Route, updatedIndex: number) => {
                 post({
                     eventType: "EMIT",
-                    solution: {
-                        time, route
-                    },
+                    route,
                     updatedIndex,
                 });
-            }) as Stats;
+            }, (progress: Progress) => {
+                post({
+                    eventType: "PROGRESS",
+                    progress,
+                });
+            }, message.data.cancelFlag) as Stats;
             post({
                 eventType: "FINISH",
                 stats,
@@ -82,23 +173,44 @@ self.addEventListener("message", (message: MessageEvent<WorkerRequest>) => {
         } catch (error) {
             post({
                 eventType: "ERROR",
-                error: new Error(error),
+                ...toError(error),
             });
         }
     } else if (message.data.eventType == "SUGGEST") {
-        let { table, maxRestarts, onlyRequiredRestarts, restartPenalty, timeToBeat } = message.data.params;
+        let { table, maxRestarts, onlyRequiredRestarts, restartPenalty, backend, precedences, optionalMaps, minOptional, start, finish, forced, forbidden, timeToBeat } = message.data.params;
 
-        suggest_solutions(table, maxRestarts, onlyRequiredRestarts, restartPenalty, timeToBeat, (start: number, end: number, time: number, route: number[]) => {
+        suggest_solutions(table, maxRestarts, onlyRequiredRestarts, restartPenalty, backend, precedences, optionalMaps, minOptional, start, finish, forced, forbidden, timeToBeat, (start: number, end: number, route: Route) => {
             post({
                 eventType: "EMIT_SUGGESTION",
                 suggestion: {
-                    start, end, time, route
+                    start, end, route
                 }
             });
+        }, (tested: number, total: number) => {
+            post({
+                eventType: "SUGGEST_PROGRESS",
+                tested,
+                total,
+            });
         });
         post({
             eventType: "FINISH_SUGGESTION",
         });
+    } else if (message.data.eventType == "EVALUATE") {
+        let { table, maxRestarts, onlyRequiredRestarts, restartPenalty, precedences, optionalMaps, minOptional, start, finish, forced, forbidden, route } = message.data.params;
+
+        try {
+            let evaluated = evaluate_route(table, maxRestarts, onlyRequiredRestarts, restartPenalty, precedences, optionalMaps, minOptional, start, finish, forced, forbidden, route) as Route;
+            post({
+                eventType: "EVALUATED",
+                route: evaluated,
+            });
+        } catch (error) {
+            post({
+                eventType: "EVALUATE_ERROR",
+                ...toError(error),
+            });
+        }
     } else {
         let _: never = message.data;
     }